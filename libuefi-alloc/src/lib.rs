@@ -7,19 +7,23 @@
 //!
 //! Call the `init` function with a reference to the boot services table.
 //! Failure to do so before calling a memory allocating function will panic.
+//!
+//! With the `mem` feature, the crate also provides `memcpy`, `memmove` and `memset`
+//! using the `CopyMem` and `SetMem` boot services.
 
 // Enable additional lints.
 #![warn(missing_docs)]
 #![deny(clippy::all)]
 #![no_std]
-// Custom allocators are currently unstable.
-#![feature(allocator_api)]
 
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr;
 
 use libuefi_rs::tables::boot::BootServices;
-use libuefi_rs::utility::{PhysAddress, MemoryType};
+use libuefi_rs::utility::MemoryType;
+
+#[cfg(all(feature = "mem", target_os = "uefi"))]
+mod mem;
 
 /// Reference to the boot services table, used to call the pool memory allocation functions.
 static mut BOOT_SERVICES: Option<&BootServices> = None;
@@ -31,6 +35,15 @@ pub fn init(boot_services: &'static BootServices) {
     }
 }
 
+/// Stops using the boot services, must be called before exiting them.
+///
+/// Allocating panics afterwards.
+pub fn exit_boot_services() {
+    unsafe {
+        BOOT_SERVICES = None;
+    }
+}
+
 fn boot_services() -> &'static BootServices {
     unsafe { BOOT_SERVICES.unwrap() }
 }
@@ -53,15 +66,17 @@ unsafe impl GlobalAlloc for Allocator {
         } else {
             boot_services()
                 .allocate_pool(mem_ty, size)
-                .map(|addr| addr.unwrap().get() as *mut _)
+                .map(|buffer| buffer.unwrap())
                 .unwrap_or(ptr::null_mut())
         }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        let _ = boot_services().free_pool(PhysAddress::from_mut_u8(ptr)).unwrap();
+        let _ = boot_services().free_pool(ptr).unwrap();
     }
 }
 
+// Host builds, such as tests, keep the allocator of `std`.
+#[cfg(target_os = "uefi")]
 #[global_allocator]
 static ALLOCATOR: Allocator = Allocator;
\ No newline at end of file
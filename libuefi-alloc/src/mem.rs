@@ -0,0 +1,48 @@
+//! `memcpy`, `memmove` and `memset` backed by the boot services, enabled by the `mem` feature.
+//!
+//! They replace the routines of `compiler_builtins` to keep small images small.
+//! Before `init` and after `exit_boot_services`, they fall back to copying byte by byte.
+
+use core::ptr;
+
+use crate::BOOT_SERVICES;
+
+/// Copies `n` bytes, the regions must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    memmove(dest, src, n)
+}
+
+/// Copies `n` bytes, the regions may overlap.
+#[no_mangle]
+pub unsafe extern "C" fn memmove(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    match BOOT_SERVICES {
+        Some(boot_services) => boot_services.copy_mem_raw(dest, src, n),
+        // Volatile accesses keep the loops from being turned back into calls to these functions.
+        None if (dest as usize) <= (src as usize) => {
+            for i in 0 .. n {
+                ptr::write_volatile(dest.add(i), ptr::read_volatile(src.add(i)));
+            }
+        },
+        None => {
+            for i in (0 .. n).rev() {
+                ptr::write_volatile(dest.add(i), ptr::read_volatile(src.add(i)));
+            }
+        }
+    }
+    dest
+}
+
+/// Fills `n` bytes with the low byte of `c`.
+#[no_mangle]
+pub unsafe extern "C" fn memset(dest: *mut u8, c: i32, n: usize) -> *mut u8 {
+    match BOOT_SERVICES {
+        Some(boot_services) => boot_services.set_mem_raw(dest, n, c as u8),
+        None => {
+            for i in 0 .. n {
+                ptr::write_volatile(dest.add(i), c as u8);
+            }
+        }
+    }
+    dest
+}
@@ -0,0 +1,350 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Shell Specification, 3.4 Command-Line Parsing
+
+//* Use from external library *//
+use crate::alloc::string::{String, ToString};
+use crate::alloc::vec::Vec;
+use core::fmt;
+use libuefi_rs::protocols::loaded_image::LoadedImage;
+use libuefi_rs::protocols::shell_parameters::ShellParameters;
+use libuefi_rs::status::{Result, Status};
+use libuefi_rs::utility::CStr16;
+
+//* Use from local library *//
+use crate::{boot_services, image_handle};
+use crate::protocols::loaded_image::LoadedImageExt;
+use crate::string::CString16;
+
+//* Constants & Types *//
+const SPACE: u16 = b' ' as u16;
+const TAB: u16 = b'\t' as u16;
+const QUOTE: u16 = b'"' as u16;
+const ESCAPE: u16 = b'^' as u16;
+
+/// Returns the arguments of the running image, without the image name.
+///
+/// When started by the UEFI Shell, they come from the Shell Parameters protocol
+/// and its first argument, the image name as typed, is skipped.
+/// Otherwise the load options, e.g. the optional data of a boot entry, are split by `split_command_line`
+/// and kept as is: they usually hold no image name, so none is skipped.
+pub fn arguments() -> Result<Vec<CString16>> {
+    if let Some(parameters) = unsafe { boot_services().handle_protocol::<ShellParameters>(image_handle()) } {
+        let parameters = unsafe { &*parameters.as_ptr() };
+        let args = parameters.args().skip(1).map(|arg| arg.map(CString16::from)).collect::<core::result::Result<Vec<_>, _>>()?;
+        return Ok(args.into());
+    }
+
+    let loaded_image = LoadedImage::current()?.unwrap();
+    let args = match loaded_image.load_options_cstr16() {
+        Some(options) => split_command_line(options),
+        None => Vec::new()
+    };
+    Ok(args.into())
+}
+
+/// Splits a command line like the UEFI Shell does.
+///
+/// Arguments are separated by spaces or tabs, double quotes group an argument containing them
+/// and `^` makes the next character literal, e.g. `^"` or `^^`.
+pub fn split_command_line(command_line: &CStr16) -> Vec<CString16> {
+    let mut args = Vec::new();
+    let mut current: Option<Vec<u16>> = None;
+    let mut quoted = false;
+    let mut codes = command_line.as_slice().iter().cloned();
+
+    while let Some(code) = codes.next() {
+        match code {
+            SPACE | TAB if !quoted => {
+                if let Some(mut arg) = current.take() {
+                    arg.push(0);
+                    args.push(arg);
+                }
+            },
+            QUOTE => {
+                quoted = !quoted;
+                current.get_or_insert_with(Vec::new);
+            },
+            // A trailing `^` escapes nothing and is dropped.
+            ESCAPE => if let Some(next) = codes.next() {
+                current.get_or_insert_with(Vec::new).push(next);
+            },
+            code => current.get_or_insert_with(Vec::new).push(code)
+        }
+    }
+    if let Some(mut arg) = current {
+        arg.push(0);
+        args.push(arg);
+    }
+
+    // The code units come from a valid string and nulls are never added.
+    args.into_iter().map(|arg| CString16::from_vec_with_nul(arg).unwrap()).collect()
+}
+
+/// A declarative parser for options and positional arguments.
+///
+/// Options are written `--name`, `-name` or, for a short name, `-n`.
+/// Values follow the option as the next argument or after `=`, e.g. `--output=log.txt`.
+/// Short flags can be combined, e.g. `-vq`, and `--` ends the options.
+#[derive(Clone, Debug, Default)]
+pub struct ArgParser {
+    options: Vec<ArgOption>
+}
+
+#[derive(Clone, Debug)]
+struct ArgOption {
+    name: &'static str,
+    short: Option<char>,
+    takes_value: bool,
+    help: &'static str
+}
+
+/// The options and positional arguments found by `ArgParser::parse`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Matches {
+    flags: Vec<&'static str>,
+    values: Vec<(&'static str, String)>,
+    positional: Vec<String>
+}
+
+/// Error parsing the arguments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ArgError {
+    /// The option is not declared.
+    UnknownOption(String),
+    /// The option needs a value but none was given.
+    MissingValue(&'static str),
+    /// The flag was given a value.
+    UnexpectedValue(&'static str)
+}
+
+impl ArgParser {
+    pub fn new() -> Self {
+        ArgParser::default()
+    }
+
+    /// Declares an option without value.
+    pub fn flag(mut self, name: &'static str, short: Option<char>, help: &'static str) -> Self {
+        self.options.push(ArgOption { name, short, takes_value: false, help });
+        self
+    }
+
+    /// Declares an option taking a value.
+    pub fn option(mut self, name: &'static str, short: Option<char>, help: &'static str) -> Self {
+        self.options.push(ArgOption { name, short, takes_value: true, help });
+        self
+    }
+
+    /// Parses the arguments, e.g. those returned by `arguments`.
+    pub fn parse(&self, args: &[CString16]) -> core::result::Result<Matches, ArgError> {
+        let mut matches = Matches::default();
+        let mut args = args.iter().map(|arg| arg.to_string());
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                matches.positional.extend(args);
+                break;
+            }
+
+            let (name, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+                split_value(long)
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                let (name, value) = split_value(&arg[1 ..]);
+                match self.find_long(name) {
+                    Some(_) => (name, value),
+                    None => {
+                        self.parse_short(&arg[1 ..], &mut args, &mut matches)?;
+                        continue;
+                    }
+                }
+            } else {
+                matches.positional.push(arg);
+                continue;
+            };
+
+            let option = self.find_long(name).ok_or_else(|| ArgError::UnknownOption(arg.clone()))?;
+            match (option.takes_value, inline_value) {
+                (true, Some(value)) => matches.values.push((option.name, String::from(value))),
+                (true, None) => {
+                    let value = args.next().ok_or(ArgError::MissingValue(option.name))?;
+                    matches.values.push((option.name, value));
+                },
+                (false, Some(_)) => return Err(ArgError::UnexpectedValue(option.name)),
+                (false, None) => matches.flags.push(option.name)
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Returns a description of the options, one per line.
+    pub fn usage(&self) -> String {
+        let mut usage = String::new();
+        for option in &self.options {
+            let short = option.short.map(|c| format!("-{}, ", c)).unwrap_or_default();
+            let value = if option.takes_value { " <value>" } else { "" };
+            usage += &format!("  {}--{}{}\t{}\n", short, option.name, value, option.help);
+        }
+        usage
+    }
+
+    /// Parses a cluster of short options, the last one may take a value.
+    fn parse_short<I>(&self, cluster: &str, args: &mut I, matches: &mut Matches) -> core::result::Result<(), ArgError>
+        where I: Iterator<Item = String>
+    {
+        for (offset, c) in cluster.char_indices() {
+            let option = self.options.iter()
+                .find(|option| option.short == Some(c))
+                .ok_or_else(|| ArgError::UnknownOption(format!("-{}", c)))?;
+            if !option.takes_value {
+                matches.flags.push(option.name);
+                continue;
+            }
+
+            let rest = &cluster[offset + c.len_utf8() ..];
+            let value = match rest.strip_prefix('=').unwrap_or(rest) {
+                "" => args.next().ok_or(ArgError::MissingValue(option.name))?,
+                value => String::from(value)
+            };
+            matches.values.push((option.name, value));
+            break;
+        }
+        Ok(())
+    }
+
+    fn find_long(&self, name: &str) -> Option<&ArgOption> {
+        self.options.iter().find(|option| option.name == name)
+    }
+}
+
+impl Matches {
+    /// Returns whether a flag was given.
+    pub fn is_present(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+
+    /// Returns the value of an option, the last one if it was given several times.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.iter().rev().find(|(option, _)| *option == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all values of an option, in order.
+    pub fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.values.iter().filter(move |(option, _)| *option == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the arguments which are not options.
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgError::UnknownOption(ref option) => write!(f, "unknown option {}", option),
+            ArgError::MissingValue(name) => write!(f, "missing value for --{}", name),
+            ArgError::UnexpectedValue(name) => write!(f, "--{} does not take a value", name)
+        }
+    }
+}
+
+impl From<ArgError> for Status {
+    fn from(_: ArgError) -> Self {
+        Status::InvalidParameter
+    }
+}
+
+fn split_value(option: &str) -> (&str, Option<&str>) {
+    match option.find('=') {
+        Some(index) => (&option[.. index], Some(&option[index + 1 ..])),
+        None => (option, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::TryFrom;
+    use libuefi_rs::cstr16;
+
+    fn args(args: &[&str]) -> Vec<CString16> {
+        args.iter().map(|arg| CString16::try_from(*arg).unwrap()).collect()
+    }
+
+    fn parser() -> ArgParser {
+        ArgParser::new()
+            .flag("verbose", Some('v'), "Prints more")
+            .flag("quiet", Some('q'), "Prints less")
+            .option("name", Some('n'), "Sets the name")
+            .option("output", Some('o'), "Sets the output file")
+    }
+
+    #[test]
+    fn split_separators() {
+        assert_eq!(split_command_line(cstr16!("a  b\tc\t")), args(&["a", "b", "c"]));
+        assert_eq!(split_command_line(cstr16!(" \t ")), args(&[]));
+        assert_eq!(split_command_line(cstr16!("")), args(&[]));
+    }
+
+    #[test]
+    fn split_quotes() {
+        assert_eq!(split_command_line(cstr16!("\"a b\" c")), args(&["a b", "c"]));
+        assert_eq!(split_command_line(cstr16!("x \"\" y")), args(&["x", "", "y"]));
+        assert_eq!(split_command_line(cstr16!("\"a\"b")), args(&["ab"]));
+        assert_eq!(split_command_line(cstr16!("\"a\tb")), args(&["a\tb"]));
+    }
+
+    #[test]
+    fn split_escapes() {
+        assert_eq!(split_command_line(cstr16!("^\"a b^\"")), args(&["\"a", "b\""]));
+        assert_eq!(split_command_line(cstr16!("a^ b ^^")), args(&["a b", "^"]));
+        assert_eq!(split_command_line(cstr16!("\"^\"\"")), args(&["\""]));
+        assert_eq!(split_command_line(cstr16!("a ^")), args(&["a"]));
+        assert_eq!(split_command_line(cstr16!("a^")), args(&["a"]));
+    }
+
+    #[test]
+    fn parse_long() {
+        let matches = parser().parse(&args(&["--name=x=y", "--output", "log.txt", "--verbose", "-name", "z"])).unwrap();
+        assert_eq!(matches.values("name").collect::<Vec<_>>(), vec!["x=y", "z"]);
+        assert_eq!(matches.value("name"), Some("z"));
+        assert_eq!(matches.value("output"), Some("log.txt"));
+        assert!(matches.is_present("verbose"));
+        assert!(!matches.is_present("quiet"));
+    }
+
+    #[test]
+    fn parse_short() {
+        let matches = parser().parse(&args(&["-n", "x", "-vq", "-ovalue", "-vo=log.txt"])).unwrap();
+        assert_eq!(matches.value("name"), Some("x"));
+        assert_eq!(matches.values("output").collect::<Vec<_>>(), vec!["value", "log.txt"]);
+        assert!(matches.is_present("verbose"));
+        assert!(matches.is_present("quiet"));
+    }
+
+    #[test]
+    fn parse_positional() {
+        let matches = parser().parse(&args(&["a", "-", "-v", "--", "-q", "--name"])).unwrap();
+        assert_eq!(matches.positional(), &["a", "-", "-q", "--name"]);
+        assert!(matches.is_present("verbose"));
+        assert!(!matches.is_present("quiet"));
+        assert_eq!(matches.value("name"), None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parser = parser();
+        assert_eq!(parser.parse(&args(&["--unknown"])), Err(ArgError::UnknownOption(String::from("--unknown"))));
+        assert_eq!(parser.parse(&args(&["-vx"])), Err(ArgError::UnknownOption(String::from("-x"))));
+        assert_eq!(parser.parse(&args(&["--name"])), Err(ArgError::MissingValue("name")));
+        assert_eq!(parser.parse(&args(&["-vn"])), Err(ArgError::MissingValue("name")));
+        assert_eq!(parser.parse(&args(&["--verbose=yes"])), Err(ArgError::UnexpectedValue("verbose")));
+        assert_eq!(ArgError::MissingValue("name").to_string(), "missing value for --name");
+    }
+}
@@ -0,0 +1,203 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Specification, 8.2.2 Using the EFI_VARIABLE_AUTHENTICATION_2 descriptor
+
+//* Use from external library *//
+use crate::alloc::vec::Vec;
+use libuefi_rs::status::Result;
+use libuefi_rs::utility::{Guid, Time, CStr16};
+use libuefi_rs::utility::guid::CERT_TYPE_PKCS7_GUID;
+use libuefi_rs::tables::runtime::VariableAttributes;
+
+//* Use from local library *//
+use crate::runtime_services;
+use crate::runtime::RuntimeServicesExt;
+use crate::secure_boot::SignatureDatabaseKind;
+use crate::string::CString16;
+
+//* Constants & Types *//
+const WIN_CERT_REVISION: u16 = 0x0200;
+const WIN_CERT_TYPE_EFI_GUID: u16 = 0x0EF1;
+/// `WIN_CERTIFICATE` header plus the certificate type GUID.
+const WIN_CERTIFICATE_UEFI_GUID_SIZE: usize = 24;
+
+/// Builds the payload of a time-based authenticated variable write,
+/// an `EFI_VARIABLE_AUTHENTICATION_2` descriptor followed by the variable data.
+///
+/// The PKCS#7 signature must have been created beforehand over `signed_data()`.
+pub struct AuthenticatedVariableBuilder<'a> {
+    name: CString16,
+    vendor: Guid,
+    attributes: VariableAttributes,
+    timestamp: Time,
+    signature: &'a [u8],
+    data: &'a [u8]
+}
+
+impl<'a> AuthenticatedVariableBuilder<'a> {
+    /// Starts a payload for a non-volatile variable accessible at boot time and runtime.
+    pub fn new(name: &CStr16, vendor: Guid, timestamp: Time) -> Self {
+        AuthenticatedVariableBuilder {
+            name: CString16::from(name),
+            vendor,
+            attributes: VariableAttributes::NON_VOLATILE
+                | VariableAttributes::BOOTSERVICE_ACCESS
+                | VariableAttributes::RUNTIME_ACCESS
+                | VariableAttributes::TIME_BASED_AUTHENTICATED_WRITE_ACCESS,
+            timestamp,
+            signature: &[],
+            data: &[]
+        }
+    }
+
+    /// Starts a payload for `PK`, `KEK`, `db` or `dbx`.
+    pub fn for_database(kind: SignatureDatabaseKind, timestamp: Time) -> Self {
+        AuthenticatedVariableBuilder::new(kind.name(), kind.vendor(), timestamp)
+    }
+
+    /// Sets the DER-encoded PKCS#7 `SignedData`.
+    pub fn signature(mut self, signature: &'a [u8]) -> Self {
+        self.signature = signature;
+        self
+    }
+
+    /// Sets the new variable data, e.g. serialized `EFI_SIGNATURE_LIST`s.
+    pub fn data(mut self, data: &'a [u8]) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Appends the data to the existing value instead of replacing it.
+    pub fn append(mut self, append: bool) -> Self {
+        self.attributes.set(VariableAttributes::APPEND_WRITE, append);
+        self
+    }
+
+    /// Replaces the attributes.
+    ///
+    /// `TIME_BASED_AUTHENTICATED_WRITE_ACCESS` is always added,
+    /// firmware rejects this payload for any other kind of write.
+    pub fn attributes(mut self, attributes: VariableAttributes) -> Self {
+        self.attributes = attributes | VariableAttributes::TIME_BASED_AUTHENTICATED_WRITE_ACCESS;
+        self
+    }
+
+    /// Returns the attributes passed to `set_variable`.
+    pub fn variable_attributes(&self) -> VariableAttributes {
+        self.attributes
+    }
+
+    /// Returns the bytes the PKCS#7 signature has to cover:
+    /// the name without terminator, vendor GUID, attributes, timestamp and data.
+    pub fn signed_data(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        for c in self.name.as_slice() {
+            buffer.extend_from_slice(&c.to_le_bytes());
+        }
+        buffer.extend_from_slice(&self.vendor.to_bytes());
+        buffer.extend_from_slice(&self.attributes.bits().to_le_bytes());
+        buffer.extend_from_slice(&self.timestamp_bytes());
+        buffer.extend_from_slice(self.data);
+        buffer
+    }
+
+    /// Assembles the `EFI_VARIABLE_AUTHENTICATION_2` descriptor followed by the data.
+    pub fn build(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(16 + WIN_CERTIFICATE_UEFI_GUID_SIZE + self.signature.len() + self.data.len());
+        buffer.extend_from_slice(&self.timestamp_bytes());
+        buffer.extend_from_slice(&((WIN_CERTIFICATE_UEFI_GUID_SIZE + self.signature.len()) as u32).to_le_bytes());
+        buffer.extend_from_slice(&WIN_CERT_REVISION.to_le_bytes());
+        buffer.extend_from_slice(&WIN_CERT_TYPE_EFI_GUID.to_le_bytes());
+        buffer.extend_from_slice(&CERT_TYPE_PKCS7_GUID.to_bytes());
+        buffer.extend_from_slice(self.signature);
+        buffer.extend_from_slice(self.data);
+        buffer
+    }
+
+    /// Writes the payload through `set_variable`.
+    pub fn write(&self) -> Result<()> {
+        unsafe { runtime_services().write_variable(&self.name, &self.vendor, self.attributes, &self.build()) }
+    }
+
+    /// The timestamp with `Pad1`, `Nanosecond`, `TimeZone`, `Daylight` and `Pad2` set to zero.
+    fn timestamp_bytes(&self) -> [u8; 16] {
+        let time = &self.timestamp;
+        let year = time.year().to_le_bytes();
+        [year[0], year[1], time.month(), time.day(), time.hour(), time.minute(), time.second(),
+         0, 0, 0, 0, 0, 0, 0, 0, 0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libuefi_rs::cstr16;
+    use libuefi_rs::utility::guid::IMAGE_SECURITY_DATABASE_GUID;
+    use libuefi_rs::utility::time::{Daylight, TimeZone};
+
+    const SIGNATURE: &[u8] = &[0x30, 0x82, 0x01, 0x02, 0xAA];
+    const DATA: &[u8] = &[0x11, 0x22, 0x33];
+
+    fn timestamp() -> Time {
+        // Fields the descriptor has to zero are set on purpose.
+        Time::new(2024, 5, 17, 13, 45, 30, 123_456_789, TimeZone::from_minutes(-540).unwrap(), Daylight::ADJUST | Daylight::IN).unwrap()
+    }
+
+    fn builder() -> AuthenticatedVariableBuilder<'static> {
+        AuthenticatedVariableBuilder::for_database(SignatureDatabaseKind::Db, timestamp())
+            .signature(SIGNATURE)
+            .data(DATA)
+    }
+
+    #[test]
+    fn build_layout() {
+        let payload = builder().build();
+        assert_eq!(payload.len(), 16 + 24 + SIGNATURE.len() + DATA.len());
+
+        // EFI_TIME with only the date and time of day.
+        assert_eq!(&payload[.. 16], &[0xE8, 0x07, 5, 17, 13, 45, 30, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        // WIN_CERTIFICATE: dwLength, wRevision and wCertificateType.
+        assert_eq!(&payload[16 .. 20], &(24 + SIGNATURE.len() as u32).to_le_bytes());
+        assert_eq!(&payload[20 .. 22], &[0x00, 0x02]);
+        assert_eq!(&payload[22 .. 24], &[0xF1, 0x0E]);
+        // EFI_CERT_TYPE_PKCS7_GUID, 4aafd29d-68df-49ee-8aa9-347d375665a7.
+        assert_eq!(&payload[24 .. 40], &[0x9D, 0xD2, 0xAF, 0x4A, 0xDF, 0x68, 0xEE, 0x49,
+                                         0x8A, 0xA9, 0x34, 0x7D, 0x37, 0x56, 0x65, 0xA7]);
+        assert_eq!(&payload[40 .. 40 + SIGNATURE.len()], SIGNATURE);
+        assert_eq!(&payload[40 + SIGNATURE.len() ..], DATA);
+    }
+
+    #[test]
+    fn build_without_signature() {
+        let payload = AuthenticatedVariableBuilder::new(cstr16!("Test"), IMAGE_SECURITY_DATABASE_GUID, timestamp()).build();
+        assert_eq!(payload.len(), 40);
+        assert_eq!(&payload[16 .. 20], &[24, 0, 0, 0]);
+    }
+
+    #[test]
+    fn signed_data_order() {
+        let signed = builder().signed_data();
+        let mut expected = vec![b'd', 0, b'b', 0];
+        expected.extend_from_slice(&IMAGE_SECURITY_DATABASE_GUID.to_bytes());
+        expected.extend_from_slice(&[0x27, 0, 0, 0]);
+        expected.extend_from_slice(&[0xE8, 0x07, 5, 17, 13, 45, 30, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(DATA);
+        assert_eq!(signed, expected);
+    }
+
+    #[test]
+    fn attributes() {
+        let builder = builder().append(true);
+        assert_eq!(builder.variable_attributes().bits(), 0x67);
+        assert_eq!(&builder.signed_data()[20 .. 24], &[0x67, 0, 0, 0]);
+
+        let builder = builder.attributes(VariableAttributes::BOOTSERVICE_ACCESS);
+        assert_eq!(builder.variable_attributes(),
+                   VariableAttributes::BOOTSERVICE_ACCESS | VariableAttributes::TIME_BASED_AUTHENTICATED_WRITE_ACCESS);
+    }
+}
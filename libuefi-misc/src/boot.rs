@@ -26,12 +26,11 @@ impl BootServicesExt for BootServices {
 
         // Allocate a large enough buffer.
         let mut buffer = Vec::with_capacity(buffer_size);
-        buffer.set_len(buffer_size);
 
         // Perform the search.
-        let (buffer_size, status2) = self.locate_handle(search_type, Some(&mut buffer))?.split();
+        let (buffer_size, status2) = self.locate_handle(search_type, Some(buffer.spare_capacity_mut()))?.split();
 
-        // Once the vector has been filled, update its size.
+        // Keep the entries which were filled.
         buffer.set_len(buffer_size);
 
         status1
@@ -0,0 +1,364 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Specification, 3.1.3 Load Options
+
+//* Use from external library *//
+use crate::alloc::vec::Vec;
+use bitflags::bitflags;
+use libuefi_rs::protocols::device_path::{DevicePath, DevicePathList};
+use libuefi_rs::status::{Result, Status};
+use libuefi_rs::cstr16;
+use libuefi_rs::utility::{Guid, CStr16};
+use libuefi_rs::utility::guid::GLOBAL_VARIABLE_GUID;
+use libuefi_rs::tables::runtime::VariableAttributes;
+
+//* Use from local library *//
+use crate::runtime_services;
+use crate::runtime::RuntimeServicesExt;
+use crate::string::CString16;
+use crate::variable::{Variable, BootNext, NV_BS_RT};
+
+bitflags! {
+    /// Attributes of a load option.
+    pub struct LoadOptionAttributes: u32 {
+        /// The boot manager will attempt to boot this option automatically.
+        const ACTIVE = 0x00000001;
+        /// All UEFI drivers are reconnected after this driver option is loaded.
+        const FORCE_RECONNECT = 0x00000002;
+        /// The option is not shown in the boot manager menu.
+        const HIDDEN = 0x00000008;
+        /// Mask of the category bits.
+        const CATEGORY = 0x00001F00;
+        /// The option is an application, not a boot option.
+        const CATEGORY_APP = 0x00000100;
+    }
+}
+
+/// An `EFI_LOAD_OPTION`, the content of a `Boot####` variable.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoadOption {
+    pub attributes: LoadOptionAttributes,
+    pub description: CString16,
+    /// Packed device paths, the first one locates the image to load.
+    pub file_path_list: Vec<u8>,
+    pub optional_data: Vec<u8>
+}
+
+impl LoadOption {
+    /// Creates an active load option for the image at `file_path`.
+    pub fn new(description: &CStr16, file_path: &DevicePath) -> Self {
+        LoadOption {
+            attributes: LoadOptionAttributes::ACTIVE,
+            description: CString16::from(description),
+            file_path_list: unsafe { file_path.as_bytes().to_vec() },
+            optional_data: Vec::new()
+        }
+    }
+
+    /// Parses a serialized `EFI_LOAD_OPTION`.
+    pub fn parse(data: &[u8]) -> core::result::Result<Self, Status> {
+        if data.len() < 6 {
+            return Err(Status::BadBufferSize);
+        }
+
+        let attributes = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let file_path_list_length = u16::from_le_bytes([data[4], data[5]]) as usize;
+
+        let mut description = Vec::new();
+        let mut offset = 6;
+        loop {
+            if offset + 2 > data.len() {
+                return Err(Status::CompromisedData);
+            }
+            let c = u16::from_le_bytes([data[offset], data[offset + 1]]);
+            offset += 2;
+            description.push(c);
+            if c == 0 {
+                break;
+            }
+        }
+        let description = CString16::from_vec_with_nul(description)?;
+
+        if offset + file_path_list_length > data.len() {
+            return Err(Status::CompromisedData);
+        }
+        let file_path_list = &data[offset .. offset + file_path_list_length];
+        let optional_data = &data[offset + file_path_list_length ..];
+
+        Ok(LoadOption {
+            // Safe: bitflags 1.x keeps undeclared bits through `bits()` and the operators,
+            // so vendor attributes survive rewriting the option.
+            attributes: unsafe { LoadOptionAttributes::from_bits_unchecked(attributes) },
+            description,
+            file_path_list: file_path_list.to_vec(),
+            optional_data: optional_data.to_vec()
+        })
+    }
+
+    /// Serializes the load option into an `EFI_LOAD_OPTION`.
+    ///
+    /// Returns `Status::BadBufferSize` if the file path list does not fit its 16-bit length.
+    pub fn to_bytes(&self) -> core::result::Result<Vec<u8>, Status> {
+        if self.file_path_list.len() > u16::MAX as usize {
+            return Err(Status::BadBufferSize);
+        }
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.attributes.bits().to_le_bytes());
+        data.extend_from_slice(&(self.file_path_list.len() as u16).to_le_bytes());
+        for c in self.description.as_slice_with_nul() {
+            data.extend_from_slice(&c.to_le_bytes());
+        }
+        data.extend_from_slice(&self.file_path_list);
+        data.extend_from_slice(&self.optional_data);
+        Ok(data)
+    }
+
+    /// Returns the device paths of the file path list.
+    pub fn file_paths(&self) -> DevicePathList<'_> {
+        DevicePathList::new(&self.file_path_list)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.attributes.contains(LoadOptionAttributes::ACTIVE)
+    }
+}
+
+/// The ordered list of boot options the boot manager attempts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BootOrder(pub Vec<u16>);
+
+impl Variable for BootOrder {
+    const NAME: &'static CStr16 = cstr16!("BootOrder");
+    const VENDOR: Guid = GLOBAL_VARIABLE_GUID;
+    const ATTRIBUTES: VariableAttributes = NV_BS_RT;
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|n| n.to_le_bytes().to_vec()).collect()
+    }
+
+    fn decode(data: &[u8]) -> core::result::Result<Self, Status> {
+        if data.len() % 2 != 0 {
+            return Err(Status::BadBufferSize);
+        }
+        Ok(BootOrder(data.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect()))
+    }
+}
+
+/// Returns the variable name of a boot option, e.g. `Boot0001`.
+pub fn boot_option_name(number: u16) -> CString16 {
+    format!("Boot{:04X}", number).parse().unwrap()
+}
+
+/// Returns the boot option number of a `Boot####` variable name.
+pub fn parse_boot_option_name(name: &CStr16) -> Option<u16> {
+    let name = name.as_slice();
+    if name.len() != 8 || !name.starts_with(cstr16!("Boot").as_slice()) {
+        return None;
+    }
+    // The digits are uppercase hexadecimal.
+    name[4 ..].iter().try_fold(0u16, |number, &c| {
+        let digit = match c {
+            0x30 ..= 0x39 => c - 0x30,
+            0x41 ..= 0x46 => c - 0x41 + 10,
+            _ => return None
+        };
+        Some(number << 4 | digit)
+    })
+}
+
+/// Reads the `Boot####` variable with the given number.
+pub fn read_boot_option(number: u16) -> Result<LoadOption> {
+    let ((data, _), status) = unsafe { runtime_services().read_variable(&boot_option_name(number), &GLOBAL_VARIABLE_GUID)?.split() };
+    let option = LoadOption::parse(&data)?;
+    status.into_with(|| option)
+}
+
+/// Writes the `Boot####` variable with the given number.
+pub fn write_boot_option(number: u16, option: &LoadOption) -> Result<()> {
+    let data = option.to_bytes()?;
+    unsafe { runtime_services().write_variable(&boot_option_name(number), &GLOBAL_VARIABLE_GUID, NV_BS_RT, &data) }
+}
+
+/// Returns the numbers of all existing `Boot####` variables.
+pub fn boot_option_numbers() -> Result<Vec<u16>> {
+    let mut numbers = Vec::new();
+    for entry in unsafe { runtime_services().variable_names() } {
+        let (name, vendor) = entry?;
+        if vendor == GLOBAL_VARIABLE_GUID {
+            if let Some(number) = parse_boot_option_name(&name) {
+                numbers.push(number);
+            }
+        }
+    }
+    Ok(numbers.into())
+}
+
+/// Returns the boot options in `BootOrder`, skipping entries whose variable is missing.
+pub fn boot_options() -> Result<Vec<(u16, LoadOption)>> {
+    let (order, status) = BootOrder::load()?.split();
+    let mut options = Vec::new();
+    for number in order.0 {
+        match read_boot_option(number) {
+            Ok(option) => options.push((number, option.unwrap())),
+            Err(Status::NotFound) => {},
+            Err(status) => return Err(status)
+        }
+    }
+    status.into_with(|| options)
+}
+
+/// Creates a new boot option and inserts it into `BootOrder`, first or last.
+///
+/// Returns the number of the new `Boot####` variable.
+/// If `BootOrder` cannot be updated, the new variable is deleted again.
+pub fn add_boot_option(option: &LoadOption, first: bool) -> Result<u16> {
+    let used = boot_option_numbers()?.unwrap();
+    let number = (0..=0xFFFF).find(|n| !used.contains(n)).ok_or(Status::OutOfResources)?;
+    let _ = write_boot_option(number, option)?;
+
+    let mut order = match BootOrder::load() {
+        Ok(order) => order.unwrap(),
+        Err(Status::NotFound) => BootOrder::default(),
+        Err(status) => return Err(discard_boot_option(number, status))
+    };
+    if first {
+        order.0.insert(0, number);
+    } else {
+        order.0.push(number);
+    }
+    if let Err(status) = order.store() {
+        return Err(discard_boot_option(number, status));
+    }
+
+    Ok(number.into())
+}
+
+/// Deletes a boot option created by `add_boot_option`, returning the error that caused it.
+fn discard_boot_option(number: u16, status: Status) -> Status {
+    let _ = unsafe { runtime_services().remove_variable(&boot_option_name(number), &GLOBAL_VARIABLE_GUID) };
+    status
+}
+
+/// Deletes a boot option and removes it from `BootOrder`, and `BootNext` if it points at it.
+pub fn remove_boot_option(number: u16) -> Result<()> {
+    match BootOrder::load() {
+        Ok(order) => {
+            let mut order = order.unwrap();
+            order.0.retain(|&n| n != number);
+            let _ = order.store()?;
+        },
+        Err(Status::NotFound) => {},
+        Err(status) => return Err(status)
+    }
+    match BootNext::load() {
+        Ok(next) if next.unwrap() == BootNext(number) => {
+            let _ = BootNext::delete()?;
+        },
+        Ok(_) | Err(Status::NotFound) => {},
+        Err(status) => return Err(status)
+    }
+    unsafe { runtime_services().remove_variable(&boot_option_name(number), &GLOBAL_VARIABLE_GUID) }
+}
+
+/// Replaces `BootOrder`.
+pub fn set_boot_order(order: &[u16]) -> Result<()> {
+    BootOrder(order.to_vec()).store()
+}
+
+/// Boots the given option on the next boot only.
+pub fn set_boot_next(number: u16) -> Result<()> {
+    BootNext(number).store()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const END_OF_PATH: [u8; 4] = [0x7F, 0xFF, 0x04, 0x00];
+
+    fn option() -> LoadOption {
+        LoadOption {
+            attributes: LoadOptionAttributes::ACTIVE | LoadOptionAttributes::HIDDEN,
+            description: CString16::from(cstr16!("Linux")),
+            file_path_list: END_OF_PATH.to_vec(),
+            optional_data: vec![1, 2, 3]
+        }
+    }
+
+    #[test]
+    fn load_option_layout() {
+        let data = option().to_bytes().unwrap();
+        assert_eq!(&data[.. 6], &[0x09, 0, 0, 0, 4, 0]);
+        assert_eq!(&data[6 .. 18], &[b'L', 0, b'i', 0, b'n', 0, b'u', 0, b'x', 0, 0, 0]);
+        assert_eq!(&data[18 .. 22], &END_OF_PATH);
+        assert_eq!(&data[22 ..], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn load_option_round_trip() {
+        let option = option();
+        assert_eq!(LoadOption::parse(&option.to_bytes().unwrap()), Ok(option));
+
+        let empty = LoadOption { description: CString16::new(), optional_data: Vec::new(), ..self::option() };
+        assert_eq!(LoadOption::parse(&empty.to_bytes().unwrap()), Ok(empty));
+    }
+
+    #[test]
+    fn load_option_unknown_attributes() {
+        // A vendor bit and an undeclared category.
+        let bits = 0x8000_0201 | LoadOptionAttributes::FORCE_RECONNECT.bits();
+        let mut data = option().to_bytes().unwrap();
+        data[.. 4].copy_from_slice(&bits.to_le_bytes());
+
+        let option = LoadOption::parse(&data).unwrap();
+        assert_eq!(option.attributes.bits(), bits);
+        assert!(option.is_active());
+        assert_eq!(option.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn load_option_too_long() {
+        let mut option = option();
+        option.file_path_list = vec![0; 0x10000];
+        assert_eq!(option.to_bytes(), Err(Status::BadBufferSize));
+        option.file_path_list.pop();
+        assert!(option.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn malformed_load_options() {
+        let data = option().to_bytes().unwrap();
+        // Shorter than the fixed header.
+        assert_eq!(LoadOption::parse(&data[.. 5]), Err(Status::BadBufferSize));
+        // The description is not terminated.
+        assert_eq!(LoadOption::parse(&data[.. 14]), Err(Status::CompromisedData));
+        // The description ends on half a character.
+        assert_eq!(LoadOption::parse(&data[.. 7]), Err(Status::CompromisedData));
+        // The file path list is longer than the data.
+        assert_eq!(LoadOption::parse(&data[.. 21]), Err(Status::CompromisedData));
+        // The description contains a lone surrogate.
+        let mut surrogate = data.clone();
+        surrogate[6 .. 8].copy_from_slice(&0xD800u16.to_le_bytes());
+        assert_eq!(LoadOption::parse(&surrogate), Err(Status::CompromisedData));
+    }
+
+    #[test]
+    fn boot_option_names() {
+        assert_eq!(boot_option_name(0x1A), "Boot001A");
+        assert_eq!(parse_boot_option_name(&boot_option_name(0xBEEF)), Some(0xBEEF));
+        assert_eq!(parse_boot_option_name(cstr16!("Boot0000")), Some(0));
+        assert_eq!(parse_boot_option_name(cstr16!("BootFFFF")), Some(0xFFFF));
+        assert_eq!(parse_boot_option_name(cstr16!("Boot00ab")), None);
+        assert_eq!(parse_boot_option_name(cstr16!("Boot000")), None);
+        assert_eq!(parse_boot_option_name(cstr16!("Boot00001")), None);
+        assert_eq!(parse_boot_option_name(cstr16!("BootOrder")), None);
+        assert_eq!(parse_boot_option_name(cstr16!("Driver01")), None);
+        assert_eq!(parse_boot_option_name(cstr16!("")), None);
+    }
+}
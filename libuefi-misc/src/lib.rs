@@ -6,9 +6,7 @@
 // =======================================================================
 
 #![no_std]
-#![feature(panic_info_message)]
-#![feature(alloc_error_handler)]
-#![feature(alloc)]
+#![allow(clippy::missing_safety_doc)]
 extern crate libuefi_rs;
 extern crate libuefi_alloc;
 #[macro_use]
@@ -18,29 +16,44 @@ extern crate alloc;
 pub mod marcros;
 
 pub mod io;
+pub mod args;
+pub mod auth_variable;
 pub mod boot;
+pub mod boot_manager;
+pub mod memory_map;
+pub mod runtime;
 pub mod protocols;
+pub mod secure_boot;
 pub mod string;
+pub mod variable;
 
 //* Use from external library *//
 use libuefi_rs::protocols::graphics_output::{GraphicsOutput, SimpleMode};
 use libuefi_rs::tables::{BootServices, RuntimeServices, SystemTable};
 use libuefi_rs::tables::runtime::ResetType;
-use libuefi_rs::status::Status;
+use libuefi_rs::status::{Status, ResultExt};
 use libuefi_rs::protocols::console::output::TextOutput;
+use libuefi_rs::utility::Handle;
 
 //* Constants & Types *//
+static mut IMAGE_HANDLE: Option<Handle> = None;
 static mut CONSOLE: Option<*mut TextOutput> = None;
 static mut BOOT_SERVICES: Option<&BootServices> = None;
 static mut RUNTIME_SERVICES: Option<&RuntimeServices> = None;
 
-pub unsafe fn init(system_table: &'static mut SystemTable) {
+pub unsafe fn init(image_handle: Handle, system_table: &'static mut SystemTable) {
+    IMAGE_HANDLE = Some(image_handle);
     CONSOLE = Some(system_table.console_out as *mut _);
     BOOT_SERVICES = Some(system_table.boot_services());
     RUNTIME_SERVICES = Some(system_table.runtime_services());
     libuefi_alloc::init(boot_services());
 }
 
+/// Returns the handle of the running image.
+pub unsafe fn image_handle() -> Handle {
+    IMAGE_HANDLE.unwrap()
+}
+
 pub unsafe fn console() -> &'static mut TextOutput {
     &mut *(CONSOLE.unwrap())
 }
@@ -53,8 +66,8 @@ pub unsafe fn runtime_services() -> &'static RuntimeServices {
     RUNTIME_SERVICES.unwrap()
 }
 
-pub unsafe fn shutdown() {
-    let _ = runtime_services().reset_system(ResetType::EfiResetShutdown, Status::Success);
+pub unsafe fn shutdown() -> ! {
+    runtime_services().reset_system(ResetType::EfiResetShutdown, Status::Success)
 }
 
 pub unsafe fn set_max_text_mode() {
@@ -65,8 +78,7 @@ pub unsafe fn set_max_text_mode() {
     let output = console();
 
     for i in 0..(output.mode().max_mode as usize) {
-        let (query_mode, status) = output.query_mode(i).unwrap().split();
-        if status.is_success() {
+        if let Ok(query_mode) = output.query_mode(i).warning_as_error() {
             let w = query_mode.0;
             let h = query_mode.1;
             if w >= max_w && h >= max_h {
@@ -86,8 +98,7 @@ pub unsafe fn set_max_graphics_mode(output: &mut GraphicsOutput) {
     let mut max_mode = SimpleMode::default();
 
     for i in 0..(output.mode().max_mode() as usize) {
-        let (mode, status) = output.query_mode(i as u32).unwrap().split();
-        if status.is_success() {
+        if let Ok(mode) = output.query_mode(i as u32).warning_as_error() {
             let w = mode.width();
             let h = mode.height();
             if w >= max_mode.width() && h >= max_mode.height() {
@@ -99,8 +110,9 @@ pub unsafe fn set_max_graphics_mode(output: &mut GraphicsOutput) {
     let _ = output.set_mode(&max_mode);
 }
 
+#[cfg(target_os = "uefi")]
 #[panic_handler]
-pub extern fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+pub fn panic_handler(info: &core::panic::PanicInfo) -> ! {
     if let Some(location) = info.location() {
         print!(
             "Panic in {} at ({}, {}):",
@@ -108,17 +120,7 @@ pub extern fn panic_handler(info: &core::panic::PanicInfo) -> ! {
             location.line(),
             location.column()
         );
-        if let Some(message) = info.message() {
-            print!(" {}", message);
-        }
+        print!(" {}", info.message());
     }
     loop {}
 }
-
-#[alloc_error_handler]
-fn out_of_memory(layout: ::core::alloc::Layout) -> ! {
-    panic!(
-        "Ran out of free memory while trying to allocate {:#?}",
-        layout
-    );
-}
\ No newline at end of file
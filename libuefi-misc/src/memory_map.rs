@@ -0,0 +1,393 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+//! Analysis and conversion of memory maps.
+//!
+//! The functions take descriptors laid out as `MemoryDescriptor`s,
+//! maps returned by the firmware, whose descriptor size may be larger, are copied by `descriptors` first.
+
+//* Use from external library *//
+use crate::alloc::vec::Vec;
+use libuefi_rs::utility::{MemoryAttribute, MemoryDescriptor, MemoryMap, MemoryType, PageRange};
+
+//* Constants & Types *//
+const MULTIBOOT2_TAG_TYPE_MMAP: u32 = 6;
+const MULTIBOOT2_MMAP_HEADER_SIZE: usize = 16;
+
+/// Copies the descriptors of a map returned by the firmware.
+pub fn descriptors(map: &MemoryMap) -> Vec<MemoryDescriptor> {
+    map.iter().collect()
+}
+
+/// Sorts the descriptors by physical start address.
+pub fn sort(map: &mut [MemoryDescriptor]) {
+    map.sort_unstable_by_key(|descriptor| descriptor.physical_start().get());
+}
+
+/// Returns the map sorted, with adjacent regions of the same type and attributes merged.
+///
+/// Merged regions keep the virtual start of their first region.
+pub fn merge(map: &[MemoryDescriptor]) -> Vec<MemoryDescriptor> {
+    let mut sorted = map.to_vec();
+    sort(&mut sorted);
+
+    let mut merged: Vec<MemoryDescriptor> = Vec::with_capacity(sorted.len());
+    for descriptor in sorted {
+        if let Some(last) = merged.last_mut() {
+            if last.physical_end() == descriptor.physical_start()
+                && last.memory_type() == descriptor.memory_type()
+                && last.attributes() == descriptor.attributes() {
+                let mut extended = MemoryDescriptor::new(last.memory_type(), last.physical_start(),
+                                                         last.page_count() + descriptor.page_count(), last.attributes());
+                extended.set_virtual_start(last.virtual_start());
+                *last = extended;
+                continue;
+            }
+        }
+        merged.push(descriptor);
+    }
+    merged
+}
+
+/// Returns the number of pages of each memory type present in the map, ordered by type.
+pub fn totals(map: &[MemoryDescriptor]) -> Vec<(MemoryType, u64)> {
+    let mut totals: Vec<(MemoryType, u64)> = Vec::new();
+    for descriptor in map {
+        match totals.iter_mut().find(|(memory_type, _)| *memory_type == descriptor.memory_type()) {
+            Some((_, pages)) => *pages += descriptor.page_count(),
+            None => totals.push((descriptor.memory_type(), descriptor.page_count()))
+        }
+    }
+    totals.sort_unstable_by_key(|(memory_type, _)| memory_type.raw());
+    totals
+}
+
+/// Returns whether the OS may use memory of this type once boot services were exited.
+pub fn is_usable_after_boot(memory_type: MemoryType) -> bool {
+    memory_type == MemoryType::EfiConventionalMemory
+        || memory_type == MemoryType::EfiBootServicesCode
+        || memory_type == MemoryType::EfiBootServicesData
+}
+
+/// Returns the regions usable after boot, sorted and merged.
+///
+/// Regions earmarked for specific purposes (`MemoryAttribute::SP`) are left out.
+pub fn usable_after_boot(map: &[MemoryDescriptor]) -> Vec<PageRange> {
+    let mut usable: Vec<MemoryDescriptor> = map.iter()
+        .filter(|descriptor| is_usable_after_boot(descriptor.memory_type()))
+        .filter(|descriptor| !descriptor.attributes().contains(MemoryAttribute::SP))
+        .cloned()
+        .collect();
+    sort(&mut usable);
+
+    let mut regions: Vec<PageRange> = Vec::with_capacity(usable.len());
+    for descriptor in usable {
+        let pages = descriptor.pages();
+        if let Some(last) = regions.last_mut() {
+            if last.end() == pages.start() {
+                *last = PageRange::new(last.start(), last.count() + pages.count());
+                continue;
+            }
+        }
+        regions.push(pages);
+    }
+    regions
+}
+
+/// Type of an E820 entry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum E820Type {
+    Ram = 1,
+    Reserved = 2,
+    Acpi = 3,
+    Nvs = 4,
+    Unusable = 5,
+    Pmem = 7,
+    SoftReserved = 0xefffffff
+}
+
+impl E820Type {
+    /// Classifies a region. Loader regions are reserved, so they survive until the OS has taken over.
+    pub fn from_descriptor(descriptor: &MemoryDescriptor) -> Self {
+        match descriptor.memory_type() {
+            memory_type if is_usable_after_boot(memory_type) => {
+                if descriptor.attributes().contains(MemoryAttribute::SP) {
+                    E820Type::SoftReserved
+                } else {
+                    E820Type::Ram
+                }
+            },
+            MemoryType::EfiACPIReclaimMemory => E820Type::Acpi,
+            MemoryType::EfiACPIMemoryNVS => E820Type::Nvs,
+            MemoryType::EfiUnusableMemory => E820Type::Unusable,
+            MemoryType::EfiPersistentMemory => E820Type::Pmem,
+            _ => E820Type::Reserved
+        }
+    }
+}
+
+/// An entry of the BIOS E820 memory map, as passed in the Linux boot parameters.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C, packed)]
+pub struct E820Entry {
+    pub address: u64,
+    pub size: u64,
+    pub entry_type: E820Type
+}
+
+/// Converts the map to a sorted E820 map, merging adjacent entries of the same type.
+pub fn to_e820(map: &[MemoryDescriptor]) -> Vec<E820Entry> {
+    let mut sorted = map.to_vec();
+    sort(&mut sorted);
+
+    let mut entries: Vec<E820Entry> = Vec::with_capacity(sorted.len());
+    for descriptor in sorted {
+        let entry_type = E820Type::from_descriptor(&descriptor);
+        if let Some(last) = entries.last_mut() {
+            let (address, size, last_type) = (last.address, last.size, last.entry_type);
+            if last_type == entry_type && address + size == descriptor.physical_start().get() {
+                last.size = size + descriptor.size();
+                continue;
+            }
+        }
+        entries.push(E820Entry {
+            address: descriptor.physical_start().get(),
+            size: descriptor.size(),
+            entry_type
+        });
+    }
+    entries
+}
+
+/// Type of a Multiboot2 memory map entry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum Multiboot2MemoryType {
+    Available = 1,
+    Reserved = 2,
+    AcpiReclaimable = 3,
+    /// Must be preserved on hibernation.
+    Nvs = 4,
+    Defective = 5
+}
+
+impl From<E820Type> for Multiboot2MemoryType {
+    fn from(entry_type: E820Type) -> Self {
+        match entry_type {
+            E820Type::Ram => Multiboot2MemoryType::Available,
+            E820Type::Acpi => Multiboot2MemoryType::AcpiReclaimable,
+            E820Type::Nvs => Multiboot2MemoryType::Nvs,
+            E820Type::Unusable => Multiboot2MemoryType::Defective,
+            E820Type::Reserved | E820Type::Pmem | E820Type::SoftReserved => Multiboot2MemoryType::Reserved
+        }
+    }
+}
+
+/// An entry of the Multiboot2 memory map tag.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct Multiboot2MemoryEntry {
+    pub base_addr: u64,
+    pub length: u64,
+    pub entry_type: Multiboot2MemoryType,
+    reserved: u32
+}
+
+/// Converts the map to sorted Multiboot2 memory map entries, classified like `to_e820`.
+pub fn to_multiboot2(map: &[MemoryDescriptor]) -> Vec<Multiboot2MemoryEntry> {
+    let mut entries: Vec<Multiboot2MemoryEntry> = Vec::new();
+    for entry in to_e820(map) {
+        let entry_type = Multiboot2MemoryType::from(entry.entry_type);
+        if let Some(last) = entries.last_mut() {
+            if last.entry_type == entry_type && last.base_addr + last.length == entry.address {
+                last.length += entry.size;
+                continue;
+            }
+        }
+        entries.push(Multiboot2MemoryEntry {
+            base_addr: entry.address,
+            length: entry.size,
+            entry_type,
+            reserved: 0
+        });
+    }
+    entries
+}
+
+/// Serializes the map into a Multiboot2 memory map tag (type 6), including its header.
+pub fn multiboot2_tag(map: &[MemoryDescriptor]) -> Vec<u8> {
+    let entries = to_multiboot2(map);
+    let entry_size = core::mem::size_of::<Multiboot2MemoryEntry>();
+    let size = MULTIBOOT2_MMAP_HEADER_SIZE + entries.len() * entry_size;
+
+    let mut tag = Vec::with_capacity(size);
+    tag.extend_from_slice(&MULTIBOOT2_TAG_TYPE_MMAP.to_le_bytes());
+    tag.extend_from_slice(&(size as u32).to_le_bytes());
+    tag.extend_from_slice(&(entry_size as u32).to_le_bytes());
+    // Entry version
+    tag.extend_from_slice(&0u32.to_le_bytes());
+    for entry in entries {
+        tag.extend_from_slice(&entry.base_addr.to_le_bytes());
+        tag.extend_from_slice(&entry.length.to_le_bytes());
+        tag.extend_from_slice(&(entry.entry_type as u32).to_le_bytes());
+        tag.extend_from_slice(&entry.reserved.to_le_bytes());
+    }
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libuefi_rs::utility::{PhysAddress, VirtAddress, PAGE_SIZE};
+
+    fn region(memory_type: MemoryType, start: u64, pages: u64) -> MemoryDescriptor {
+        MemoryDescriptor::new(memory_type, PhysAddress::new(start), pages, MemoryAttribute::WB)
+    }
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+    }
+
+    fn read_u64(data: &[u8], offset: usize) -> u64 {
+        read_u32(data, offset) as u64 | (read_u32(data, offset + 4) as u64) << 32
+    }
+
+    #[test]
+    fn merge_adjacent_regions() {
+        let mut first = region(MemoryType::EfiConventionalMemory, 0x1000, 2);
+        first.set_virtual_start(VirtAddress::new(0x8000_1000));
+        let map = [
+            region(MemoryType::EfiConventionalMemory, 0x3000, 1),
+            first,
+            region(MemoryType::EfiConventionalMemory, 0x4000, 4)
+        ];
+
+        let merged = merge(&map);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].physical_start().get(), 0x1000);
+        assert_eq!(merged[0].page_count(), 7);
+        assert_eq!(merged[0].virtual_start().get(), 0x8000_1000);
+    }
+
+    #[test]
+    fn merge_keeps_separate_regions() {
+        let uncached = MemoryDescriptor::new(MemoryType::EfiConventionalMemory, PhysAddress::new(0x3000), 1, MemoryAttribute::UC);
+        let map = [
+            region(MemoryType::EfiConventionalMemory, 0x1000, 1),
+            // Same type, but not adjacent.
+            region(MemoryType::EfiConventionalMemory, 0x5000, 1),
+            // Adjacent, but of another type.
+            region(MemoryType::EfiLoaderData, 0x2000, 1),
+            // Adjacent, but with other attributes.
+            uncached
+        ];
+
+        let merged = merge(&map);
+        assert_eq!(merged.len(), 4);
+        let starts = merged.iter().map(|descriptor| descriptor.physical_start().get()).collect::<Vec<_>>();
+        assert_eq!(starts, vec![0x1000, 0x2000, 0x3000, 0x5000]);
+    }
+
+    #[test]
+    fn usable_regions() {
+        let map = [
+            region(MemoryType::EfiBootServicesData, 0x2000, 1),
+            region(MemoryType::EfiConventionalMemory, 0x1000, 1),
+            region(MemoryType::EfiRuntimeServicesData, 0x3000, 1),
+            region(MemoryType::EfiBootServicesCode, 0x4000, 2),
+            MemoryDescriptor::new(MemoryType::EfiConventionalMemory, PhysAddress::new(0x6000), 1, MemoryAttribute::WB | MemoryAttribute::SP)
+        ];
+
+        let usable = usable_after_boot(&map);
+        assert_eq!(usable.len(), 2);
+        assert_eq!((usable[0].start_address().get(), usable[0].count()), (0x1000, 2));
+        assert_eq!((usable[1].start_address().get(), usable[1].count()), (0x4000, 2));
+        assert_eq!(totals(&map)[0], (MemoryType::EfiBootServicesCode, 2));
+    }
+
+    #[test]
+    fn e820_types() {
+        let types = [
+            (MemoryType::EfiReservedMemoryType, E820Type::Reserved),
+            (MemoryType::EfiLoaderCode, E820Type::Reserved),
+            (MemoryType::EfiLoaderData, E820Type::Reserved),
+            (MemoryType::EfiBootServicesCode, E820Type::Ram),
+            (MemoryType::EfiBootServicesData, E820Type::Ram),
+            (MemoryType::EfiRuntimeServicesCode, E820Type::Reserved),
+            (MemoryType::EfiRuntimeServicesData, E820Type::Reserved),
+            (MemoryType::EfiConventionalMemory, E820Type::Ram),
+            (MemoryType::EfiUnusableMemory, E820Type::Unusable),
+            (MemoryType::EfiACPIReclaimMemory, E820Type::Acpi),
+            (MemoryType::EfiACPIMemoryNVS, E820Type::Nvs),
+            (MemoryType::EfiMemoryMappedIO, E820Type::Reserved),
+            (MemoryType::EfiMemoryMappedIOPortSpace, E820Type::Reserved),
+            (MemoryType::EfiPalCode, E820Type::Reserved),
+            (MemoryType::EfiPersistentMemory, E820Type::Pmem),
+            (MemoryType::EfiUnacceptedMemoryType, E820Type::Reserved),
+            (MemoryType::from_raw(0x70000000), E820Type::Reserved),
+            (MemoryType::from_raw(0x80000000), E820Type::Reserved)
+        ];
+        for &(memory_type, entry_type) in types.iter() {
+            assert_eq!(E820Type::from_descriptor(&region(memory_type, 0, 1)), entry_type, "{:?}", memory_type);
+        }
+
+        let specific = MemoryDescriptor::new(MemoryType::EfiConventionalMemory, PhysAddress::new(0), 1, MemoryAttribute::SP);
+        assert_eq!(E820Type::from_descriptor(&specific), E820Type::SoftReserved);
+    }
+
+    #[test]
+    fn e820_merges_by_type() {
+        let map = [
+            region(MemoryType::EfiConventionalMemory, 0x0, 1),
+            region(MemoryType::EfiBootServicesData, 0x1000, 1),
+            region(MemoryType::EfiACPIReclaimMemory, 0x2000, 1),
+            region(MemoryType::EfiConventionalMemory, 0x4000, 1)
+        ];
+
+        let entries = to_e820(&map);
+        let entries = entries.iter().map(|entry| (entry.address, entry.size, entry.entry_type)).collect::<Vec<_>>();
+        assert_eq!(entries, vec![
+            (0x0, 2 * PAGE_SIZE, E820Type::Ram),
+            (0x2000, PAGE_SIZE, E820Type::Acpi),
+            (0x4000, PAGE_SIZE, E820Type::Ram)
+        ]);
+    }
+
+    #[test]
+    fn multiboot2_tag_layout() {
+        let map = [
+            region(MemoryType::EfiConventionalMemory, 0x0, 1),
+            region(MemoryType::EfiPersistentMemory, 0x1000, 1),
+            region(MemoryType::EfiReservedMemoryType, 0x2000, 1),
+            region(MemoryType::EfiACPIMemoryNVS, 0x3000, 2)
+        ];
+
+        let tag = multiboot2_tag(&map);
+        let entry_size = 24;
+        // Persistent and reserved memory are both reserved for Multiboot2 and merge.
+        assert_eq!(tag.len(), 16 + 3 * entry_size);
+        assert_eq!(tag.len() % 8, 0);
+        assert_eq!(read_u32(&tag, 0), 6);
+        assert_eq!(read_u32(&tag, 4) as usize, tag.len());
+        assert_eq!(read_u32(&tag, 8) as usize, entry_size);
+        assert_eq!(read_u32(&tag, 12), 0);
+
+        let entries = tag[16 ..].chunks(entry_size)
+            .map(|entry| (read_u64(entry, 0), read_u64(entry, 8), read_u32(entry, 16), read_u32(entry, 20)))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![
+            (0x0, PAGE_SIZE, 1, 0),
+            (0x1000, 2 * PAGE_SIZE, 2, 0),
+            (0x3000, 2 * PAGE_SIZE, 4, 0)
+        ]);
+
+        let empty = multiboot2_tag(&[]);
+        assert_eq!(empty.len(), 16);
+        assert_eq!(read_u32(&empty, 4), 16);
+    }
+}
@@ -9,45 +9,61 @@
 use crate::alloc::vec::Vec;
 use libuefi_rs::protocols::file_system::{SimpleFileSystem, File, FileOpenMode, FileAttr};
 use libuefi_rs::status::{Result, Status};
+use libuefi_rs::utility::CStr16;
 
 //* Use from local library *//
 use crate::boot_services;
 use crate::boot::BootServicesExt;
-use crate::string::wstr;
+use crate::protocols::loaded_image::image_location;
 
 pub trait SimpleFileSystemExt {
-    fn new() -> &'static mut Self 
+    fn new() -> Result<&'static mut Self>
+        where Self: Sized;
+    fn first() -> Result<&'static mut Self>
         where Self: Sized;
 }
 
 impl SimpleFileSystemExt for SimpleFileSystem {
-    fn new() -> &'static mut Self {
-        unsafe { &mut *(boot_services().find_protocol::<SimpleFileSystem>().unwrap().as_ptr()) }
+    /// Returns the volume the running image was loaded from.
+    ///
+    /// Fails if it cannot be determined, e.g. for an image loaded from memory,
+    /// use `first` to fall back to any volume.
+    fn new() -> Result<&'static mut Self> {
+        image_location().map(|location| location.map(|(file_system, _)| file_system))
+    }
+
+    /// Returns the first volume found, which is not necessarily the one of the running image.
+    fn first() -> Result<&'static mut Self> {
+        match unsafe { boot_services().find_protocol::<SimpleFileSystem>() } {
+            Some(file_system) => Ok(unsafe { &mut *file_system.as_ptr() }.into()),
+            None => Err(Status::NotFound)
+        }
     }
 }
 
 pub trait FileExt {
     fn root() -> Result<Self>
         where Self: Sized;
-    fn find(path: &str) -> Result<Self>
+    fn find(path: &CStr16) -> Result<Self>
         where Self: Sized;
-    fn load(path: &str) -> Vec<u8>
+    fn load(path: &CStr16) -> Vec<u8>
         where Self: Sized;
-    fn open(&mut self, path: &str, mode: FileOpenMode, attr: FileAttr) -> Result<Self>
+    fn open(&mut self, path: &CStr16, mode: FileOpenMode, attr: FileAttr) -> Result<Self>
         where Self: Sized;
     fn read(&mut self, vec: &mut Vec<u8>) -> Result<usize>
         where Self: Sized;
 }
 
 impl FileExt for File {
+    /// Opens the root directory of the volume the running image was loaded from.
     fn root() -> Result<Self> {
         unsafe { 
-            let simple_file_system = SimpleFileSystem::new();
+            let simple_file_system = SimpleFileSystem::new()?.unwrap();
             simple_file_system.open()
         }
     }
 
-    fn find(path: &str) -> Result<Self> {
+    fn find(path: &CStr16) -> Result<Self> {
         let mut root = File::root()?.unwrap();
         match root.open(path, FileOpenMode::Read, FileAttr::Normal) {
             Ok(file) => {
@@ -60,22 +76,21 @@ impl FileExt for File {
         Err(Status::NotFound)
     }
 
-    fn load(path: &str) -> Vec<u8> {
+    fn load(path: &CStr16) -> Vec<u8> {
         let mut file = File::find(path).unwrap().unwrap();
         let mut data: Vec<u8> = vec![];
         let _ = file.read(&mut data);
         data
     }
 
-    fn open(&mut self, path: &str, mode: FileOpenMode, attr: FileAttr) -> Result<Self> {
-        let wpath = &wstr(path);
-        unsafe { self.inner_open(wpath, mode, attr) }
+    fn open(&mut self, path: &CStr16, mode: FileOpenMode, attr: FileAttr) -> Result<Self> {
+        unsafe { self.inner_open(path, mode, attr) }
     }
 
     fn read(&mut self, vec: &mut Vec<u8>) -> Result<usize> {
         let mut total = 0;
-        let mut status = Status::Success;
-        
+        let mut status;
+
         loop {
             let mut buf = [0; 8192];
 
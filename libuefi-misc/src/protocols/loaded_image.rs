@@ -0,0 +1,105 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+//* Use from external library *//
+use crate::alloc::vec::Vec;
+use libuefi_rs::protocols::device_path::{DevicePath, DevicePathType, MediaSubType};
+use libuefi_rs::protocols::file_system::SimpleFileSystem;
+use libuefi_rs::protocols::loaded_image::{LoadedImage, LoadedImageDevicePath};
+use libuefi_rs::status::{Result, Status};
+
+//* Use from local library *//
+use crate::{boot_services, image_handle};
+use crate::string::CString16;
+
+pub trait LoadedImageExt {
+    /// Returns the Loaded Image protocol of the running image.
+    fn current() -> Result<&'static Self>
+        where Self: Sized;
+
+    /// Returns the path of the image file on its volume, e.g. `\EFI\BOOT\BOOTX64.EFI`.
+    ///
+    /// Returns `None` if the file path holds no file path nodes or they are malformed.
+    fn file_path_string(&self) -> Option<CString16>;
+
+    /// Returns the directory containing the image file, e.g. `\EFI\BOOT`.
+    fn directory(&self) -> Option<CString16>;
+}
+
+impl LoadedImageExt for LoadedImage {
+    fn current() -> Result<&'static Self> {
+        match unsafe { boot_services().handle_protocol::<LoadedImage>(image_handle()) } {
+            Some(loaded_image) => Ok(unsafe { &*loaded_image.as_ptr() }.into()),
+            None => Err(Status::Unsupported)
+        }
+    }
+
+    fn file_path_string(&self) -> Option<CString16> {
+        file_path_string(self.file_path()?)
+    }
+
+    fn directory(&self) -> Option<CString16> {
+        let path = self.file_path_string()?;
+        let path = path.as_slice();
+        let separator = path.iter().rposition(|&c| c == b'\\' as u16)?;
+        // Keep the separator of the root directory.
+        let mut directory = path[.. separator.max(1)].to_vec();
+        directory.push(0);
+        CString16::from_vec_with_nul(directory).ok()
+    }
+}
+
+/// Returns the file system the running image was loaded from and the directory containing the image.
+///
+/// The volume is located through the Loaded Image Device Path protocol if present,
+/// falling back to the device handle of the Loaded Image protocol.
+pub fn image_location() -> Result<(&'static mut SimpleFileSystem, CString16)> {
+    let loaded_image = LoadedImage::current()?.unwrap();
+    let file_system = unsafe { image_file_system(loaded_image) }.ok_or(Status::NotFound)?;
+    let directory = loaded_image.directory().unwrap_or_else(|| "\\".parse().unwrap());
+    Ok((file_system, directory).into())
+}
+
+unsafe fn image_file_system(loaded_image: &LoadedImage) -> Option<&'static mut SimpleFileSystem> {
+    let boot_services = boot_services();
+
+    let located = boot_services.handle_protocol::<LoadedImageDevicePath>(image_handle())
+        .and_then(|device_path| {
+            let mut device_path: &DevicePath = &*device_path.as_ptr();
+            boot_services.locate_device_path::<SimpleFileSystem>(&mut device_path).ok()
+        })
+        .map(|device| device.unwrap());
+
+    let device = located.or_else(|| loaded_image.device_handle())?;
+    boot_services.handle_protocol::<SimpleFileSystem>(device).map(|file_system| &mut *file_system.as_ptr())
+}
+
+/// Concatenates the media file path nodes of a device path.
+fn file_path_string(device_path: &DevicePath) -> Option<CString16> {
+    let mut path: Vec<u16> = Vec::new();
+    let mut node = device_path;
+    while !node.is_end_entire() && !node.is_end_instance() {
+        if node.device_type() == DevicePathType::Media as u8 && node.sub_type() == MediaSubType::MediaFilePathDP as u8 {
+            // Nodes are packed, so the UCS-2 data is not necessarily aligned.
+            let data = unsafe { node.data() };
+            let name = data.chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0);
+            if path.last().is_some_and(|&c| c != b'\\' as u16) {
+                path.push(b'\\' as u16);
+            }
+            path.extend(name);
+        }
+        node = unsafe { node.next() };
+    }
+
+    if path.is_empty() {
+        return None;
+    }
+    path.push(0);
+    CString16::from_vec_with_nul(path).ok()
+}
@@ -6,4 +6,5 @@
 // =======================================================================
 
 pub mod graphics_output;
-pub mod file_system;
\ No newline at end of file
+pub mod file_system;
+pub mod loaded_image;
\ No newline at end of file
@@ -0,0 +1,163 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+//* Use from external library *//
+use crate::alloc::vec::Vec;
+use core::mem;
+use libuefi_rs::status::{Result, Status};
+use libuefi_rs::utility::{Guid, PhysAddress, CStr16};
+use libuefi_rs::tables::runtime::{RuntimeServices, VariableAttributes, ResetType, CapsuleHeader, CapsuleBlockDescriptor};
+
+//* Use from local library *//
+use crate::string::CString16;
+
+//* Constants & Types *//
+const INITIAL_NAME_LEN: usize = 256;
+
+pub trait RuntimeServicesExt {
+    /// Returns the value and attributes of a variable.
+    unsafe fn read_variable(&self, name: &CStr16, vendor: &Guid) -> Result<(Vec<u8>, VariableAttributes)>;
+
+    /// Sets the value of a variable.
+    unsafe fn write_variable(&self, name: &CStr16, vendor: &Guid, attributes: VariableAttributes, data: &[u8]) -> Result<()>;
+
+    /// Deletes a variable.
+    unsafe fn remove_variable(&self, name: &CStr16, vendor: &Guid) -> Result<()>;
+
+    /// Returns an iterator over the names and vendor GUIDs of all variables.
+    unsafe fn variable_names(&self) -> VariableNames<'_>;
+
+    /// Resets the platform, recording `reason` in the reset data.
+    unsafe fn reset_with_reason(&self, reset_type: ResetType, reset_status: Status, reason: &CStr16) -> !;
+
+    /// Requests the platform-specific reset identified by `reset_guid`, followed by `data`.
+    unsafe fn reset_platform_specific(&self, reset_status: Status, reason: &CStr16, reset_guid: &Guid, data: &[u8]) -> !;
+
+    /// Passes capsules to the firmware along with their scatter-gather list.
+    ///
+    /// The list is leaked on purpose, the firmware may still read it during the next reset.
+    unsafe fn update_capsules(&self, capsules: &[&CapsuleHeader]) -> Result<()>;
+}
+
+impl RuntimeServicesExt for RuntimeServices {
+    unsafe fn read_variable(&self, name: &CStr16, vendor: &Guid) -> Result<(Vec<u8>, VariableAttributes)> {
+        // Determine how much we need to allocate.
+        let ((size, _), _) = self.get_variable(name, vendor, None)?.split();
+
+        let mut buffer = vec![0; size];
+        let ((size, attributes), status) = self.get_variable(name, vendor, Some(&mut buffer))?.split();
+        buffer.truncate(size);
+
+        status.into_with(|| (buffer, attributes))
+    }
+
+    unsafe fn write_variable(&self, name: &CStr16, vendor: &Guid, attributes: VariableAttributes, data: &[u8]) -> Result<()> {
+        self.set_variable(name, vendor, attributes, data)
+    }
+
+    unsafe fn remove_variable(&self, name: &CStr16, vendor: &Guid) -> Result<()> {
+        self.delete_variable(name, vendor)
+    }
+
+    unsafe fn variable_names(&self) -> VariableNames<'_> {
+        VariableNames {
+            runtime_services: self,
+            name: vec![0; INITIAL_NAME_LEN],
+            vendor: Guid::default(),
+            finished: false
+        }
+    }
+
+    unsafe fn reset_with_reason(&self, reset_type: ResetType, reset_status: Status, reason: &CStr16) -> ! {
+        self.reset_system_with_data(reset_type, reset_status, &reset_data(reason, None, &[]))
+    }
+
+    unsafe fn reset_platform_specific(&self, reset_status: Status, reason: &CStr16, reset_guid: &Guid, data: &[u8]) -> ! {
+        let data = reset_data(reason, Some(reset_guid), data);
+        self.reset_system_with_data(ResetType::EfiResetPlatformSpecific, reset_status, &data)
+    }
+
+    unsafe fn update_capsules(&self, capsules: &[&CapsuleHeader]) -> Result<()> {
+        let list = scatter_gather_list(capsules);
+        let status = self.update_capsule(capsules, Some(&list));
+        mem::forget(list);
+        status
+    }
+}
+
+/// Builds a scatter-gather list with one block per capsule, capsules must be identity mapped.
+pub fn scatter_gather_list(capsules: &[&CapsuleHeader]) -> Vec<CapsuleBlockDescriptor> {
+    let mut list: Vec<CapsuleBlockDescriptor> = capsules.iter()
+        .map(|&capsule| {
+            let address = PhysAddress::from_ptr(capsule);
+            CapsuleBlockDescriptor::data_block(address, capsule.capsule_image_size as u64)
+        })
+        .collect();
+    list.push(CapsuleBlockDescriptor::end());
+    list
+}
+
+/// Builds the reset data passed to `reset_system`:
+/// the null-terminated reason, then the platform-specific reset `Guid` if any, then `data`.
+pub fn reset_data(reason: &CStr16, reset_guid: Option<&Guid>, data: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for c in reason.as_slice_with_nul() {
+        buffer.extend_from_slice(&c.to_le_bytes());
+    }
+    if let Some(guid) = reset_guid {
+        buffer.extend_from_slice(&guid.to_bytes());
+    }
+    buffer.extend_from_slice(data);
+    buffer
+}
+
+/// Iterator over the names and vendor GUIDs of all variables.
+///
+/// Yields `Status::CompromisedData` and stops if a name is not valid UCS-2.
+pub struct VariableNames<'a> {
+    runtime_services: &'a RuntimeServices,
+    name: Vec<u16>,
+    vendor: Guid,
+    finished: bool
+}
+
+impl<'a> Iterator for VariableNames<'a> {
+    type Item = core::result::Result<(CString16, Guid), Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            match unsafe { self.runtime_services.get_next_variable_name(&mut self.name, &mut self.vendor) } {
+                Ok(_) => {
+                    return match CStr16::from_u16_until_nul(&self.name) {
+                        Ok(name) => Some(Ok((CString16::from(name), self.vendor))),
+                        Err(error) => {
+                            self.finished = true;
+                            Some(Err(error.into()))
+                        }
+                    };
+                },
+                // The previous name is kept at the start of the buffer, so growing it is enough.
+                Err(ref error) if error.status() == Status::BufferTooSmall => {
+                    let len = *error.data();
+                    self.name.resize(len, 0);
+                },
+                Err(ref error) if error.status() == Status::NotFound => {
+                    self.finished = true;
+                    return None;
+                },
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(error.status()));
+                }
+            }
+        }
+    }
+}
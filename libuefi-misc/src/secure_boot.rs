@@ -0,0 +1,407 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Specification, 32.4 Firmware/OS Key Exchange
+
+//* Use from external library *//
+use crate::alloc::vec::Vec;
+use libuefi_rs::status::{Result, Status};
+use libuefi_rs::cstr16;
+use libuefi_rs::utility::{Guid, CStr16};
+use libuefi_rs::utility::guid::{GLOBAL_VARIABLE_GUID, IMAGE_SECURITY_DATABASE_GUID, CERT_SHA256_GUID, CERT_X509_GUID};
+
+//* Use from local library *//
+use crate::runtime_services;
+use crate::runtime::RuntimeServicesExt;
+
+//* Constants & Types *//
+const SIGNATURE_LIST_HEADER_SIZE: usize = 28;
+const GUID_SIZE: usize = 16;
+
+/// The Secure Boot related global variables.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SecureBootState {
+    /// `SecureBoot`: the platform is enforcing image verification.
+    pub secure_boot: bool,
+    /// `SetupMode`: no platform key is enrolled.
+    pub setup_mode: bool,
+    /// `AuditMode`: image verification results are logged but not enforced.
+    pub audit_mode: bool,
+    /// `DeployedMode`: the platform is locked down.
+    pub deployed_mode: bool
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SecureBootMode {
+    Setup,
+    User,
+    Audit,
+    Deployed
+}
+
+impl SecureBootState {
+    /// Reads the state, treating missing variables as `false`.
+    pub fn load() -> Result<Self> {
+        Ok(SecureBootState {
+            secure_boot: read_flag(cstr16!("SecureBoot"))?,
+            setup_mode: read_flag(cstr16!("SetupMode"))?,
+            audit_mode: read_flag(cstr16!("AuditMode"))?,
+            deployed_mode: read_flag(cstr16!("DeployedMode"))?
+        }.into())
+    }
+
+    pub fn mode(&self) -> SecureBootMode {
+        if self.audit_mode {
+            SecureBootMode::Audit
+        } else if self.deployed_mode {
+            SecureBootMode::Deployed
+        } else if self.setup_mode {
+            SecureBootMode::Setup
+        } else {
+            SecureBootMode::User
+        }
+    }
+
+    /// Returns whether images are actually rejected when verification fails.
+    pub fn is_enforcing(&self) -> bool {
+        self.secure_boot && !self.setup_mode && !self.audit_mode
+    }
+}
+
+fn read_flag(name: &CStr16) -> core::result::Result<bool, Status> {
+    match unsafe { runtime_services().read_variable(name, &GLOBAL_VARIABLE_GUID) } {
+        Ok(completion) => {
+            let ((data, _), _) = completion.split();
+            Ok(data.first() == Some(&1))
+        },
+        Err(Status::NotFound) => Ok(false),
+        Err(status) => Err(status)
+    }
+}
+
+/// The authenticated variables holding signature databases.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignatureDatabaseKind {
+    /// The platform key.
+    Pk,
+    /// The key exchange key database.
+    Kek,
+    /// The authorized signature database.
+    Db,
+    /// The forbidden signature database.
+    Dbx
+}
+
+impl SignatureDatabaseKind {
+    pub fn name(&self) -> &'static CStr16 {
+        match *self {
+            SignatureDatabaseKind::Pk => cstr16!("PK"),
+            SignatureDatabaseKind::Kek => cstr16!("KEK"),
+            SignatureDatabaseKind::Db => cstr16!("db"),
+            SignatureDatabaseKind::Dbx => cstr16!("dbx")
+        }
+    }
+
+    pub fn vendor(&self) -> Guid {
+        match *self {
+            SignatureDatabaseKind::Pk | SignatureDatabaseKind::Kek => GLOBAL_VARIABLE_GUID,
+            SignatureDatabaseKind::Db | SignatureDatabaseKind::Dbx => IMAGE_SECURITY_DATABASE_GUID
+        }
+    }
+}
+
+/// The content of a signature database variable, a sequence of `EFI_SIGNATURE_LIST`s.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SignatureDatabase {
+    data: Vec<u8>
+}
+
+impl SignatureDatabase {
+    pub fn new(data: Vec<u8>) -> Self {
+        SignatureDatabase { data }
+    }
+
+    /// Reads a signature database, a missing variable is an empty database.
+    pub fn load(kind: SignatureDatabaseKind) -> Result<Self> {
+        match unsafe { runtime_services().read_variable(kind.name(), &kind.vendor()) } {
+            Ok(completion) => Ok(completion.map(|(data, _)| SignatureDatabase::new(data))),
+            Err(Status::NotFound) => Ok(SignatureDatabase::default().into()),
+            Err(status) => Err(status)
+        }
+    }
+
+    /// Creates a database from a Linux efivarfs file, whose first four bytes are the attributes.
+    pub fn from_efivarfs(data: &[u8]) -> core::result::Result<Self, Status> {
+        if data.len() < 4 {
+            return Err(Status::BadBufferSize);
+        }
+        Ok(SignatureDatabase::new(data[4 ..].to_vec()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns an iterator over the signature lists.
+    pub fn lists(&self) -> SignatureLists<'_> {
+        SignatureLists::new(&self.data)
+    }
+
+    /// Returns all signatures, or `Status::CompromisedData` if the database is malformed.
+    pub fn signatures(&self) -> core::result::Result<Vec<SignatureData<'_>>, Status> {
+        let mut signatures = Vec::new();
+        for list in self.lists() {
+            signatures.extend(list?.signatures());
+        }
+        Ok(signatures)
+    }
+
+    /// Returns whether the DER-encoded X.509 certificate is enrolled.
+    pub fn contains_certificate(&self, certificate: &[u8]) -> bool {
+        self.contains(CERT_X509_GUID, certificate)
+    }
+
+    /// Returns whether the SHA-256 hash is enrolled.
+    pub fn contains_sha256(&self, hash: &[u8; 32]) -> bool {
+        self.contains(CERT_SHA256_GUID, hash)
+    }
+
+    fn contains(&self, signature_type: Guid, data: &[u8]) -> bool {
+        self.lists()
+            .filter_map(|list| list.ok())
+            .filter(|list| list.signature_type == signature_type)
+            .any(|list| list.signatures().any(|signature| signature.data == data))
+    }
+}
+
+/// An `EFI_SIGNATURE_LIST`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SignatureList<'a> {
+    pub signature_type: Guid,
+    pub header: &'a [u8],
+    signature_size: usize,
+    signatures: &'a [u8]
+}
+
+impl<'a> SignatureList<'a> {
+    /// Returns an iterator over the `EFI_SIGNATURE_DATA` entries of the list.
+    pub fn signatures(&self) -> impl Iterator<Item = SignatureData<'a>> {
+        let signature_type = self.signature_type;
+        self.signatures.chunks(self.signature_size).map(move |chunk| SignatureData {
+            signature_type,
+            owner: read_guid(chunk),
+            data: &chunk[GUID_SIZE ..]
+        })
+    }
+}
+
+/// An `EFI_SIGNATURE_DATA`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SignatureData<'a> {
+    /// The type of the list containing this signature.
+    pub signature_type: Guid,
+    /// The agent which added the signature.
+    pub owner: Guid,
+    pub data: &'a [u8]
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Signature<'a> {
+    /// A DER-encoded X.509 certificate.
+    X509(&'a [u8]),
+    /// A SHA-256 hash of an image.
+    Sha256(&'a [u8]),
+    Other(Guid, &'a [u8])
+}
+
+impl<'a> SignatureData<'a> {
+    pub fn signature(&self) -> Signature<'a> {
+        match self.signature_type {
+            CERT_X509_GUID => Signature::X509(self.data),
+            CERT_SHA256_GUID if self.data.len() == 32 => Signature::Sha256(self.data),
+            other => Signature::Other(other, self.data)
+        }
+    }
+}
+
+/// Iterator over the `EFI_SIGNATURE_LIST`s of a signature database.
+///
+/// Yields `Status::CompromisedData` once and stops if the data is malformed.
+pub struct SignatureLists<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> SignatureLists<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        SignatureLists { data }
+    }
+}
+
+impl<'a> Iterator for SignatureLists<'a> {
+    type Item = core::result::Result<SignatureList<'a>, Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = self.data;
+        self.data = &[];
+
+        if data.len() < SIGNATURE_LIST_HEADER_SIZE {
+            return Some(Err(Status::CompromisedData));
+        }
+
+        let list_size = read_u32(&data[16 ..]) as usize;
+        let header_size = read_u32(&data[20 ..]) as usize;
+        let signature_size = read_u32(&data[24 ..]) as usize;
+
+        let signatures_start = match SIGNATURE_LIST_HEADER_SIZE.checked_add(header_size) {
+            Some(start) if start <= list_size => start,
+            _ => return Some(Err(Status::CompromisedData))
+        };
+        if list_size > data.len() || signature_size <= GUID_SIZE || (list_size - signatures_start) % signature_size != 0 {
+            return Some(Err(Status::CompromisedData));
+        }
+
+        self.data = &data[list_size ..];
+        Some(Ok(SignatureList {
+            signature_type: read_guid(data),
+            header: &data[SIGNATURE_LIST_HEADER_SIZE .. signatures_start],
+            signature_size,
+            signatures: &data[signatures_start .. list_size]
+        }))
+    }
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+}
+
+fn read_guid(data: &[u8]) -> Guid {
+    let mut bytes = [0; GUID_SIZE];
+    bytes.copy_from_slice(&data[.. GUID_SIZE]);
+    Guid::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Databases in the efivarfs layout: the attributes followed by the signature lists.
+    const KEK: &[u8] = include_bytes!("../testdata/KEK.efivarfs");
+    const DB: &[u8] = include_bytes!("../testdata/db.efivarfs");
+    const DBX: &[u8] = include_bytes!("../testdata/dbx.efivarfs");
+
+    const REVOKED_HASH: [u8; 32] = [
+        0x2d, 0xac, 0x9e, 0x9a, 0x09, 0x19, 0x48, 0x7c, 0x93, 0x66, 0x8c, 0x8c, 0xe2, 0xf7, 0x09, 0xb2,
+        0x5c, 0x65, 0xed, 0x92, 0x4e, 0x28, 0x59, 0x70, 0xa8, 0x4f, 0x6d, 0x0a, 0xe0, 0x76, 0x56, 0xd6
+    ];
+
+    fn list(signature_type: Guid, list_size: u32, header_size: u32, signature_size: u32) -> Vec<u8> {
+        let mut data = signature_type.to_bytes().to_vec();
+        data.extend_from_slice(&list_size.to_le_bytes());
+        data.extend_from_slice(&header_size.to_le_bytes());
+        data.extend_from_slice(&signature_size.to_le_bytes());
+        data
+    }
+
+    fn is_malformed(data: &[u8]) -> bool {
+        let database = SignatureDatabase::new(data.to_vec());
+        let lists = database.lists().collect::<Vec<_>>();
+        lists.last() == Some(&Err(Status::CompromisedData)) && database.signatures() == Err(Status::CompromisedData)
+    }
+
+    #[test]
+    fn efivarfs_prefix() {
+        let with_prefix = SignatureDatabase::from_efivarfs(DB).unwrap();
+        assert_eq!(with_prefix.as_bytes(), &DB[4 ..]);
+        assert_eq!(with_prefix, SignatureDatabase::new(DB[4 ..].to_vec()));
+        assert_eq!(SignatureDatabase::from_efivarfs(&DB[.. 3]), Err(Status::BadBufferSize));
+        assert_eq!(SignatureDatabase::from_efivarfs(&DB[.. 4]).map(|db| db.signatures().unwrap().len()), Ok(0));
+
+        // Without stripping the attributes, the lists are misread.
+        assert!(SignatureDatabase::new(DB.to_vec()).signatures().is_err());
+    }
+
+    #[test]
+    fn certificate_databases() {
+        let kek = SignatureDatabase::from_efivarfs(KEK).unwrap();
+        let db = SignatureDatabase::from_efivarfs(DB).unwrap();
+
+        let kek_signatures = kek.signatures().unwrap();
+        assert_eq!(kek_signatures.len(), 1);
+        assert_eq!(kek_signatures[0].owner, Guid::parse("77fa9abd-0359-4d32-bd60-28f4e78f784b").unwrap());
+        let certificate = match kek_signatures[0].signature() {
+            Signature::X509(certificate) => certificate,
+            other => panic!("unexpected signature {:?}", other)
+        };
+        assert_eq!(&certificate[.. 4], &[0x30, 0x82, 0x01, 0x2c]);
+        assert_eq!(certificate.len(), 304);
+
+        let db_signatures = db.signatures().unwrap();
+        assert_eq!(db.lists().count(), 2);
+        assert_eq!(db_signatures.len(), 2);
+        for signature in &db_signatures {
+            assert!(db.contains_certificate(signature.data));
+            assert!(!kek.contains_certificate(signature.data));
+        }
+        assert!(kek.contains_certificate(certificate));
+        assert!(!db.contains_certificate(certificate));
+        assert!(!kek.contains_certificate(&certificate[.. 100]));
+    }
+
+    #[test]
+    fn hash_database() {
+        let dbx = SignatureDatabase::from_efivarfs(DBX).unwrap();
+        let lists = dbx.lists().collect::<core::result::Result<Vec<_>, _>>().unwrap();
+        assert_eq!(lists.len(), 1);
+        assert_eq!(lists[0].signature_type, CERT_SHA256_GUID);
+        assert!(lists[0].header.is_empty());
+
+        let signatures = dbx.signatures().unwrap();
+        assert_eq!(signatures.len(), 3);
+        assert_eq!(signatures[1].signature(), Signature::Sha256(&REVOKED_HASH));
+        assert!(dbx.contains_sha256(&REVOKED_HASH));
+        assert!(!dbx.contains_sha256(&[0; 32]));
+        // A hash is not a certificate even with the same bytes.
+        assert!(!dbx.contains_certificate(&REVOKED_HASH));
+    }
+
+    #[test]
+    fn malformed_lists() {
+        // Shorter than a list header.
+        assert!(is_malformed(&DBX[4 .. 30]));
+        // The list size is larger than the data.
+        assert!(is_malformed(&DBX[4 .. DBX.len() - 1]));
+        // The signatures are no larger than their owner GUID.
+        assert!(is_malformed(&list(CERT_SHA256_GUID, 28 + 32, 0, 16)));
+        assert!(is_malformed(&list(CERT_SHA256_GUID, 28, 0, 0)));
+        // The list is not a whole number of signatures.
+        let mut partial = list(CERT_SHA256_GUID, 28 + 60, 0, 48);
+        partial.resize(28 + 60, 0);
+        assert!(is_malformed(&partial));
+        // The header does not fit in the list.
+        assert!(is_malformed(&list(CERT_SHA256_GUID, 28, 4, 48)));
+        assert!(is_malformed(&list(CERT_SHA256_GUID, 28, u32::MAX, 48)));
+    }
+
+    #[test]
+    fn malformed_list_stops_iteration() {
+        let mut data = DBX[4 ..].to_vec();
+        data.extend_from_slice(&list(CERT_SHA256_GUID, 1000, 0, 48));
+        let database = SignatureDatabase::new(data);
+
+        let lists = database.lists().collect::<Vec<_>>();
+        assert_eq!(lists.len(), 2);
+        assert!(lists[0].is_ok());
+        assert_eq!(lists[1], Err(Status::CompromisedData));
+        // The valid list before the malformed one is still searched.
+        assert!(database.contains_sha256(&REVOKED_HASH));
+        assert_eq!(database.signatures(), Err(Status::CompromisedData));
+    }
+}
@@ -1,33 +1,264 @@
-//! Some code was borrowed from [System76 Firmware Update](https://github.com/system76/firmware-update)
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
 
+//* Use from external library *//
 use crate::alloc::string::String;
 use crate::alloc::vec::Vec;
-use core::char;
+use core::borrow::Borrow;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+use libuefi_rs::utility::{CStr8, CStr16};
+use libuefi_rs::utility::string::{FromStrError, FromSliceWithNulError};
 
-pub fn wstr(string: &str) -> Vec<u16> {
-    let mut wstring = vec![];
+/// Implements the conversions and comparisons an owned string shares with its borrowed form.
+macro_rules! owned_string {
+    ($owned:ident, $borrowed:ident, $from_unchecked:ident, $with_nul:ident, $from_str_with_buf:ident) => {
+        impl Default for $owned {
+            fn default() -> Self {
+                $owned::new()
+            }
+        }
+
+        impl Deref for $owned {
+            type Target = $borrowed;
+
+            fn deref(&self) -> &$borrowed {
+                unsafe { $borrowed::$from_unchecked(&self.0) }
+            }
+        }
+
+        impl AsRef<$borrowed> for $owned {
+            fn as_ref(&self) -> &$borrowed {
+                self
+            }
+        }
+
+        impl Borrow<$borrowed> for $owned {
+            fn borrow(&self) -> &$borrowed {
+                self
+            }
+        }
+
+        impl<'a> TryFrom<&'a str> for $owned {
+            type Error = FromStrError;
+
+            fn try_from(string: &'a str) -> Result<Self, FromStrError> {
+                // A UTF-8 string never has fewer bytes than characters.
+                let mut buffer = vec![0; string.len() + 1];
+                let len = $borrowed::$from_str_with_buf(string, &mut buffer)?.$with_nul().len();
+                buffer.truncate(len);
+                Ok($owned(buffer))
+            }
+        }
+
+        impl FromStr for $owned {
+            type Err = FromStrError;
+
+            fn from_str(string: &str) -> Result<Self, FromStrError> {
+                $owned::try_from(string)
+            }
+        }
+
+        impl<'a> From<&'a $borrowed> for $owned {
+            fn from(string: &'a $borrowed) -> Self {
+                $owned(string.$with_nul().to_vec())
+            }
+        }
+
+        impl fmt::Display for $owned {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&**self, f)
+            }
+        }
+
+        impl fmt::Debug for $owned {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Debug::fmt(&**self, f)
+            }
+        }
+
+        impl PartialEq<$borrowed> for $owned {
+            fn eq(&self, other: &$borrowed) -> bool {
+                **self == *other
+            }
+        }
+
+        impl<'a> PartialEq<&'a $borrowed> for $owned {
+            fn eq(&self, other: &&'a $borrowed) -> bool {
+                **self == **other
+            }
+        }
+
+        impl PartialEq<str> for $owned {
+            fn eq(&self, other: &str) -> bool {
+                **self == *other
+            }
+        }
+
+        impl<'a> PartialEq<&'a str> for $owned {
+            fn eq(&self, other: &&'a str) -> bool {
+                **self == **other
+            }
+        }
+    };
+}
+
+/// An owned null-terminated UCS-2 string.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CString16(Vec<u16>);
+
+impl CString16 {
+    /// Creates an empty string.
+    pub fn new() -> Self {
+        CString16(vec![0])
+    }
+
+    /// Takes ownership of code units ending with the only null of the string.
+    pub fn from_vec_with_nul(codes: Vec<u16>) -> Result<Self, FromSliceWithNulError> {
+        CStr16::from_u16_with_nul(&codes)?;
+        Ok(CString16(codes))
+    }
+
+    /// Appends a character, failing if UCS-2 cannot represent it.
+    pub fn push(&mut self, c: char) -> Result<(), FromStrError> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
 
-    for c in string.chars() {
-        wstring.push(c as u16);
+    /// Appends a string, failing without changes if UCS-2 cannot represent it.
+    pub fn push_str(&mut self, string: &str) -> Result<(), FromStrError> {
+        let other = CString16::try_from(string)?;
+        self.0.pop();
+        self.0.extend_from_slice(other.as_slice_with_nul());
+        Ok(())
     }
-    wstring.push(0);
 
-    wstring
+    pub fn into_vec_with_nul(self) -> Vec<u16> {
+        self.0
+    }
 }
 
-pub fn nstr(wstring: *const u16) -> String {
-    let mut string = String::new();
+/// An owned null-terminated 8-bit string with Latin-1 semantics.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CString8(Vec<u8>);
 
-    let mut i = 0;
-    loop {
-        let w = unsafe { *wstring.offset(i) };
-        i += 1;
-        if w == 0 {
-            break;
-        }
-        let c = unsafe { char::from_u32_unchecked(w as u32) };
-        string.push(c);
+impl CString8 {
+    /// Creates an empty string.
+    pub fn new() -> Self {
+        CString8(vec![0])
+    }
+
+    /// Takes ownership of bytes ending with the only null of the string.
+    pub fn from_vec_with_nul(bytes: Vec<u8>) -> Result<Self, FromSliceWithNulError> {
+        CStr8::from_bytes_with_nul(&bytes)?;
+        Ok(CString8(bytes))
+    }
+
+    /// Copies a fixed-size field, which ends at its first null or fills the whole field,
+    /// e.g. an ACPI OEM ID or a PE section name.
+    pub fn from_fixed(bytes: &[u8]) -> Self {
+        let len = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+        let mut bytes = bytes[.. len].to_vec();
+        bytes.push(0);
+        CString8(bytes)
+    }
+
+    /// Appends a character, failing if Latin-1 cannot represent it.
+    pub fn push(&mut self, c: char) -> Result<(), FromStrError> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Appends a string, failing without changes if Latin-1 cannot represent it.
+    pub fn push_str(&mut self, string: &str) -> Result<(), FromStrError> {
+        let other = CString8::try_from(string)?;
+        self.0.pop();
+        self.0.extend_from_slice(other.as_bytes_with_nul());
+        Ok(())
+    }
+
+    pub fn into_vec_with_nul(self) -> Vec<u8> {
+        self.0
     }
+}
+
+owned_string!(CString16, CStr16, from_u16_with_nul_unchecked, as_slice_with_nul, from_str_with_buf);
+owned_string!(CString8, CStr8, from_bytes_with_nul_unchecked, as_bytes_with_nul, from_str_with_buf);
 
-    string
-}
\ No newline at end of file
+impl<'a> From<&'a CStr8> for CString16 {
+    fn from(string: &'a CStr8) -> Self {
+        CString16(string.as_bytes_with_nul().iter().map(|&byte| byte as u16).collect())
+    }
+}
+
+impl From<CString8> for String {
+    fn from(string: CString8) -> Self {
+        string.chars().collect()
+    }
+}
+
+impl From<CString16> for String {
+    fn from(string: CString16) -> Self {
+        string.chars().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use libuefi_rs::cstr16;
+
+    #[test]
+    fn from_vec_with_nul() {
+        let string = CString16::from_vec_with_nul(vec![0x41, 0x42, 0]).unwrap();
+        assert_eq!(string, "AB");
+        assert_eq!(string.into_vec_with_nul(), vec![0x41, 0x42, 0]);
+
+        assert_eq!(CString16::from_vec_with_nul(vec![0x41, 0, 0x42, 0]), Err(FromSliceWithNulError::InteriorNul(1)));
+        assert_eq!(CString16::from_vec_with_nul(vec![0x41, 0x42]), Err(FromSliceWithNulError::NotNulTerminated));
+        assert_eq!(CString16::from_vec_with_nul(vec![0xD800, 0xDC00, 0]), Err(FromSliceWithNulError::InvalidChar(0)));
+        assert_eq!(CString8::from_vec_with_nul(b"a\0b\0".to_vec()), Err(FromSliceWithNulError::InteriorNul(1)));
+    }
+
+    #[test]
+    fn from_str() {
+        let string: CString16 = "EFI\\BOOT".parse().unwrap();
+        assert_eq!(string, cstr16!("EFI\\BOOT"));
+        assert_eq!(string.to_string(), "EFI\\BOOT");
+        assert_eq!(String::from(string), "EFI\\BOOT");
+
+        assert_eq!("a\0".parse::<CString16>(), Err(FromStrError::InteriorNul(1)));
+        assert_eq!("\u{1F600}".parse::<CString16>(), Err(FromStrError::InvalidChar(0)));
+        assert_eq!("\u{20AC}".parse::<CString8>(), Err(FromStrError::InvalidChar(0)));
+        assert_eq!(CString16::try_from("").unwrap().as_slice_with_nul(), &[0]);
+    }
+
+    #[test]
+    fn push() {
+        let mut string = CString16::new();
+        string.push_str("Boot").unwrap();
+        string.push('\u{e9}').unwrap();
+        assert_eq!(string, "Boot\u{e9}");
+
+        // Failed appends leave the string unchanged.
+        assert_eq!(string.push_str("x\u{1F600}"), Err(FromStrError::InvalidChar(1)));
+        assert_eq!(string.push('\0'), Err(FromStrError::InteriorNul(0)));
+        assert_eq!(string.as_slice_with_nul(), &[0x42, 0x6F, 0x6F, 0x74, 0xE9, 0]);
+    }
+
+    #[test]
+    fn conversions() {
+        let latin1 = CString8::from_fixed(b"caf\xe9\0\0\0");
+        assert_eq!(latin1.as_bytes_with_nul(), b"caf\xe9\0");
+        assert_eq!(CString8::from_fixed(b"FULL").as_bytes_with_nul(), b"FULL\0");
+        assert_eq!(CString16::from(&*latin1), "caf\u{e9}");
+        assert_eq!(String::from(latin1), "caf\u{e9}");
+        assert_eq!(CString16::from(cstr16!("x")), CString16::try_from("x").unwrap());
+        assert_eq!(format!("{:?}", CString16::try_from("a\"b").unwrap()), "\"a\\\"b\"");
+    }
+}
@@ -0,0 +1,216 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Specification, 3.3 Globally Defined Variables
+
+//* Use from external library *//
+use crate::alloc::string::String;
+use crate::alloc::vec::Vec;
+use bitflags::bitflags;
+use core::convert::TryInto;
+use libuefi_rs::status::{Result, Status};
+use libuefi_rs::cstr16;
+use libuefi_rs::utility::{Guid, CStr16};
+use libuefi_rs::utility::guid::GLOBAL_VARIABLE_GUID;
+use libuefi_rs::tables::runtime::VariableAttributes;
+
+//* Use from local library *//
+use crate::runtime_services;
+use crate::runtime::RuntimeServicesExt;
+use crate::string::CString8;
+
+/// A Rust type stored in a variable.
+///
+/// Implementors only describe where the variable lives and how it is encoded,
+/// `load`, `store` and `delete` do the rest.
+/// Variables the firmware provides read-only set `WRITABLE` to `false`.
+pub trait Variable: Sized {
+    /// Name of the variable.
+    const NAME: &'static CStr16;
+    /// Vendor GUID of the variable.
+    const VENDOR: Guid;
+    /// Attributes used when storing the variable.
+    const ATTRIBUTES: VariableAttributes;
+    /// Whether the variable may be written or deleted, `store` and `delete`
+    /// return `Status::WriteProtected` otherwise.
+    const WRITABLE: bool = true;
+
+    /// Serializes the value into the variable data.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Deserializes the value from the variable data.
+    ///
+    /// By convention, returns `Status::BadBufferSize` if the data has the wrong size
+    /// and `Status::CompromisedData` if its content is invalid.
+    fn decode(data: &[u8]) -> core::result::Result<Self, Status>;
+
+    /// Reads the variable.
+    fn load() -> Result<Self> {
+        let ((data, _), status) = unsafe { runtime_services().read_variable(Self::NAME, &Self::VENDOR)?.split() };
+        let value = Self::decode(&data)?;
+        status.into_with(|| value)
+    }
+
+    /// Writes the variable.
+    fn store(&self) -> Result<()> {
+        if !Self::WRITABLE {
+            return Err(Status::WriteProtected);
+        }
+        unsafe { runtime_services().write_variable(Self::NAME, &Self::VENDOR, Self::ATTRIBUTES, &self.encode()) }
+    }
+
+    /// Deletes the variable.
+    fn delete() -> Result<()> {
+        if !Self::WRITABLE {
+            return Err(Status::WriteProtected);
+        }
+        unsafe { runtime_services().remove_variable(Self::NAME, &Self::VENDOR) }
+    }
+}
+
+macro_rules! integer_variable {
+    ($(#[$attr:meta])* $ty:ident($inner:ty), $name:expr, $attributes:expr) => {
+        integer_variable!($(#[$attr])* $ty($inner), $name, $attributes, true);
+    };
+    ($(#[$attr:meta])* $ty:ident($inner:ty), $name:expr, $attributes:expr, $writable:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        pub struct $ty(pub $inner);
+
+        impl Variable for $ty {
+            const NAME: &'static CStr16 = $name;
+            const VENDOR: Guid = GLOBAL_VARIABLE_GUID;
+            const ATTRIBUTES: VariableAttributes = $attributes;
+            const WRITABLE: bool = $writable;
+
+            fn encode(&self) -> Vec<u8> {
+                self.0.to_le_bytes().to_vec()
+            }
+
+            fn decode(data: &[u8]) -> core::result::Result<Self, Status> {
+                let bytes = data.try_into().map_err(|_| Status::BadBufferSize)?;
+                Ok($ty(<$inner>::from_le_bytes(bytes)))
+            }
+        }
+    };
+}
+
+pub(crate) const NV_BS_RT: VariableAttributes = VariableAttributes::from_bits_truncate(
+    VariableAttributes::NON_VOLATILE.bits()
+        | VariableAttributes::BOOTSERVICE_ACCESS.bits()
+        | VariableAttributes::RUNTIME_ACCESS.bits());
+
+pub(crate) const BS_RT: VariableAttributes = VariableAttributes::from_bits_truncate(
+    VariableAttributes::BOOTSERVICE_ACCESS.bits()
+        | VariableAttributes::RUNTIME_ACCESS.bits());
+
+integer_variable!(
+    /// The firmware's boot manager timeout, in seconds, before initiating the default boot selection.
+    Timeout(u16), cstr16!("Timeout"), NV_BS_RT);
+
+integer_variable!(
+    /// The boot option that was selected for the current boot (read-only).
+    BootCurrent(u16), cstr16!("BootCurrent"), BS_RT, false);
+
+integer_variable!(
+    /// The boot option for the first boot option on the next boot only.
+    BootNext(u16), cstr16!("BootNext"), NV_BS_RT);
+
+/// The language code that the system is configured for, e.g. `en-US`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlatformLang(pub String);
+
+impl Variable for PlatformLang {
+    const NAME: &'static CStr16 = cstr16!("PlatformLang");
+    const VENDOR: Guid = GLOBAL_VARIABLE_GUID;
+    const ATTRIBUTES: VariableAttributes = NV_BS_RT;
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = self.0.as_bytes().to_vec();
+        data.push(0);
+        data
+    }
+
+    fn decode(data: &[u8]) -> core::result::Result<Self, Status> {
+        match CString8::from_fixed(data).as_ascii_str() {
+            Some(lang) => Ok(PlatformLang(String::from(lang))),
+            None => Err(Status::CompromisedData)
+        }
+    }
+}
+
+bitflags! {
+    /// Firmware features the OS requests on the next boot.
+    ///
+    /// Bits unknown to this crate are kept when the variable is loaded and stored again.
+    pub struct OsIndications: u64 {
+        const BOOT_TO_FW_UI = 0x0000000000000001;
+        const TIMESTAMP_REVOCATION = 0x0000000000000002;
+        const FILE_CAPSULE_DELIVERY_SUPPORTED = 0x0000000000000004;
+        const FMP_CAPSULE_SUPPORTED = 0x0000000000000008;
+        const CAPSULE_RESULT_VAR_SUPPORTED = 0x0000000000000010;
+        const START_OS_RECOVERY = 0x0000000000000020;
+        const START_PLATFORM_RECOVERY = 0x0000000000000040;
+        const JSON_CONFIG_DATA_REFRESH = 0x0000000000000080;
+    }
+}
+
+impl Variable for OsIndications {
+    const NAME: &'static CStr16 = cstr16!("OsIndications");
+    const VENDOR: Guid = GLOBAL_VARIABLE_GUID;
+    const ATTRIBUTES: VariableAttributes = NV_BS_RT;
+
+    fn encode(&self) -> Vec<u8> {
+        self.bits().to_le_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> core::result::Result<Self, Status> {
+        let bytes = data.try_into().map_err(|_| Status::BadBufferSize)?;
+        // Safe: bitflags 1.x keeps undeclared bits through `bits()` and the operators.
+        Ok(unsafe { OsIndications::from_bits_unchecked(u64::from_le_bytes(bytes)) })
+    }
+}
+
+/// Firmware features supported for `OsIndications` (read-only).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OsIndicationsSupported(pub OsIndications);
+
+impl Variable for OsIndicationsSupported {
+    const NAME: &'static CStr16 = cstr16!("OsIndicationsSupported");
+    const VENDOR: Guid = GLOBAL_VARIABLE_GUID;
+    const ATTRIBUTES: VariableAttributes = BS_RT;
+    const WRITABLE: bool = false;
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.encode()
+    }
+
+    fn decode(data: &[u8]) -> core::result::Result<Self, Status> {
+        OsIndications::decode(data).map(OsIndicationsSupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_indications_keep_unknown_bits() {
+        let data = 0x8000_0000_0000_0081u64.to_le_bytes();
+        let indications = OsIndications::decode(&data).unwrap();
+        assert!(indications.contains(OsIndications::BOOT_TO_FW_UI | OsIndications::JSON_CONFIG_DATA_REFRESH));
+        assert_eq!(indications.encode(), data.to_vec());
+        assert_eq!(OsIndications::decode(&data[.. 4]), Err(Status::BadBufferSize));
+    }
+
+    #[test]
+    fn read_only_variables() {
+        assert_eq!(BootCurrent(1).store(), Err(Status::WriteProtected));
+        assert_eq!(OsIndicationsSupported::delete(), Err(Status::WriteProtected));
+        assert_eq!(BootCurrent::delete(), Err(Status::WriteProtected));
+    }
+}
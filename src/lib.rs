@@ -6,9 +6,12 @@
 // =======================================================================
 
 #![no_std]
-#![feature(try_trait)]
-#![feature(try_from)]
-#![feature(panic_info_message)]
+// Most wrappers are unsafe because they call into the firmware, documenting each of them would be noise.
+#![allow(clippy::missing_safety_doc)]
+#![allow(clippy::module_inception)]
+
+#[macro_use] extern crate bitflags;
+#[cfg(test)] #[macro_use] extern crate std;
 
 #[macro_use] mod macros;
 pub mod tables;
@@ -1,13 +1,62 @@
 //! Some code was borrowed from [Tifflin](https://github.com/thepowersgang/rust_os)
 
+/// Declares a function using the UEFI calling convention of the target,
+/// e.g. `win64` on x86_64 and the C convention on AArch64, IA-32 and RISC-V.
 macro_rules! efi_fcn {
 	(fn $name:ident ( $($n:ident: $t:ty),* ) -> $rv:ty) => {
-		extern "win64" fn $name( $($n: $t),* ) -> $rv
+		extern "efiapi" fn $name( $($n: $t),* ) -> $rv
 	};
 	(fn ( $($n:ident: $t:ty),* ) -> $rv:ty) => {
-		unsafe extern "win64" fn( $($n: $t),* ) -> $rv
+		unsafe extern "efiapi" fn( $($n: $t),* ) -> $rv
 	};
 	(fn ( $($t:ty),* ) -> $rv:ty) => {
-		unsafe extern "win64" fn( $($t),* ) -> $rv
+		unsafe extern "efiapi" fn( $($t),* ) -> $rv
 	};
 }
+
+
+/// Defines a `Guid` from its canonical form at compile time, e.g.
+/// `guid!("8be4df61-93ca-11d2-aa0d-00e098032b8c")`.
+#[macro_export]
+macro_rules! guid {
+	($string:expr) => {{
+		const GUID: $crate::utility::Guid = match $crate::utility::Guid::parse($string) {
+			::core::option::Option::Some(guid) => guid,
+			::core::option::Option::None => panic!("invalid GUID literal")
+		};
+		GUID
+	}};
+}
+
+/// Defines a null-terminated UCS-2 string at compile time, e.g. `cstr16!("BootOrder")`.
+///
+/// Fails to compile if the literal contains a null or a character outside the Basic Multilingual Plane.
+///
+/// ```
+/// # #[macro_use] extern crate libuefi_rs;
+/// # fn main() {
+/// assert_eq!(cstr16!("Größe").as_slice(), &[0x47, 0x72, 0xF6, 0xDF, 0x65]);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate libuefi_rs;
+/// # fn main() {
+/// let _ = cstr16!("a\0b");
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate libuefi_rs;
+/// # fn main() {
+/// let _ = cstr16!("\u{1F980}");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! cstr16 {
+	($string:expr) => {{
+		const LEN: usize = $crate::utility::string::literal_len($string);
+		const CODES: [u16; LEN] = $crate::utility::string::encode_literal::<LEN>($string);
+		unsafe { $crate::utility::string::CStr16::from_u16_with_nul_unchecked(&CODES) }
+	}};
+}
\ No newline at end of file
@@ -5,9 +5,6 @@
 //   https://opensource.org/licenses/BSD-3-Clause)
 // =======================================================================
 
-//* Use from external library *//
-use core::mem;
-
 //* Use from local library *//
 use utility::Event;
 use status::{Result, Status};
@@ -32,7 +29,7 @@ impl TextInput {
     }
 
     pub fn read_key_stroke(&mut self) -> Result<TextInputKey> {
-        let mut input = unsafe { mem::uninitialized() };
+        let mut input = TextInputKey::default();
         unsafe { (self.read_key_stroke)(self, &mut input).into_with(|| input) }
     }
 }
\ No newline at end of file
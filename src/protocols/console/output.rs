@@ -7,6 +7,11 @@
 
 //* Use from local library *//
 use status::{Result, Status};
+use utility::CStr16;
+
+//* Constants & Types *//
+/// Length of the buffer `output_string` converts through, in code units.
+const OUTPUT_BUFFER_LEN: usize = 128;
 
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
@@ -38,24 +43,48 @@ impl TextOutput {
         unsafe { (self.reset)(self, extended).into() }
     }
 
+    /// Writes a string, translating `\n` to `\r\n`.
+    ///
+    /// Characters UCS-2 cannot represent are written as `U+FFFD`, nulls are skipped.
     pub fn output_string(&mut self, string: &str) -> Result<()> {
+        let mut buffer = [0u16; OUTPUT_BUFFER_LEN];
+        let mut len = 0;
         let mut status = Status::Success;
         for c in string.chars() {
-            status = unsafe { (self.output_string)(self, [c as u16, 0].as_ptr()) };
-            if c == '\n' {
-                status = unsafe { (self.output_string)(self, ['\r' as u16, 0].as_ptr()) };
+            // Leave room for `\r\n` and the terminator.
+            if len + 3 > buffer.len() {
+                buffer[len] = 0;
+                status = unsafe { (self.output_string)(self, buffer.as_ptr()) };
+                len = 0;
+            }
+            match c {
+                '\0' => {},
+                '\n' => {
+                    buffer[len] = '\r' as u16;
+                    buffer[len + 1] = '\n' as u16;
+                    len += 2;
+                },
+                c => {
+                    buffer[len] = if (c as u32) > 0xFFFF { char::REPLACEMENT_CHARACTER as u16 } else { c as u16 };
+                    len += 1;
+                }
             }
         }
+        if len > 0 {
+            buffer[len] = 0;
+            status = unsafe { (self.output_string)(self, buffer.as_ptr()) };
+        }
         status.into()
     }
 
-    pub fn test_string(&mut self, string: *const u16) -> bool {
-        unsafe { 
-            match (self.test_string)(self, string) {
-                Status::Success => true,
-                _ => false
-            }
-        }
+    /// Writes a UCS-2 string as is.
+    pub fn output_cstr16(&mut self, string: &CStr16) -> Result<()> {
+        unsafe { (self.output_string)(self, string.as_ptr()).into() }
+    }
+
+    /// Returns whether the device can display all characters of the string.
+    pub fn test_string(&mut self, string: &CStr16) -> bool {
+        unsafe { (self.test_string)(self, string.as_ptr()) == Status::Success }
     }
 
     pub fn query_mode(&mut self, index: usize) -> Result<(usize, usize)> {
@@ -85,6 +114,6 @@ impl TextOutput {
     }
 
     pub fn mode(&self) -> &'static TextOutputMode {
-        (self.mode)
+        self.mode
     }
 }
\ No newline at end of file
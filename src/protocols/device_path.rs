@@ -7,13 +7,123 @@
 
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_DEVICE_PATH_PROTOCOL
 
+//* Use from external library *//
+use core::slice;
+
+//* Use from local library *//
+use super::Protocol;
+use utility::Guid;
+use utility::guid::DEVICE_PATH_GUID;
+
 #[repr(C)]
 pub struct DevicePath {
-    ty: DevicePathType,
+    ty: u8,
     sub_type: u8,
     len: [u8; 2]
 }
 
+impl Protocol for DevicePath {
+    const GUID: Guid = DEVICE_PATH_GUID;
+}
+
+impl DevicePath {
+    /// Interprets `bytes` as a device path, returns `None` if it is not terminated by an end node.
+    pub fn from_bytes(bytes: &[u8]) -> Option<&DevicePath> {
+        path_size(bytes).map(|_| unsafe { &*(bytes.as_ptr() as *const DevicePath) })
+    }
+
+    pub fn device_type(&self) -> u8 {
+        self.ty
+    }
+
+    pub fn sub_type(&self) -> u8 {
+        self.sub_type
+    }
+
+    /// Length of this node in bytes, including the header.
+    pub fn length(&self) -> usize {
+        u16::from_le_bytes(self.len) as usize
+    }
+
+    pub fn is_end_entire(&self) -> bool {
+        self.ty == DevicePathType::End as u8 && self.sub_type == EndSubType::EndEntireDPS as u8
+    }
+
+    pub fn is_end_instance(&self) -> bool {
+        self.ty == DevicePathType::End as u8 && self.sub_type == EndSubType::EndInstanceDPS as u8
+    }
+
+    /// Returns the node data following the header.
+    pub unsafe fn data(&self) -> &[u8] {
+        let len = self.length().saturating_sub(4);
+        slice::from_raw_parts((self as *const DevicePath as *const u8).add(4), len)
+    }
+
+    /// Returns the next node, must not be called on the end of the entire device path.
+    pub unsafe fn next(&self) -> &DevicePath {
+        &*((self as *const DevicePath as *const u8).add(self.length()) as *const DevicePath)
+    }
+
+    /// Returns the entire device path starting at this node, including the end node.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        let mut size = 0;
+        let mut node = self;
+        loop {
+            size += node.length();
+            if node.is_end_entire() {
+                break;
+            }
+            node = node.next();
+        }
+        slice::from_raw_parts(self as *const DevicePath as *const u8, size)
+    }
+}
+
+/// Iterator over the device paths packed in a buffer, such as the file path list of a load option.
+pub struct DevicePathList<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> DevicePathList<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        DevicePathList { bytes }
+    }
+}
+
+impl<'a> Iterator for DevicePathList<'a> {
+    type Item = &'a DevicePath;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.bytes;
+        match path_size(bytes) {
+            Some(size) => {
+                self.bytes = &bytes[size ..];
+                DevicePath::from_bytes(bytes)
+            },
+            None => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+/// Returns the size of the device path at the start of `bytes`, including the end node.
+fn path_size(bytes: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    loop {
+        let header = bytes.get(offset .. offset + 4)?;
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        if len < 4 || offset + len > bytes.len() {
+            return None;
+        }
+        offset += len;
+        if header[0] == DevicePathType::End as u8 && header[1] == EndSubType::EndEntireDPS as u8 {
+            return Some(offset);
+        }
+    }
+}
+
 #[repr(u8)]
 pub enum DevicePathType {
     Hardware = 0x01,
@@ -8,14 +8,11 @@
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_SIMPLE_FILE_SYSTEM_PROTOCOL
 //! Some code was borrowed from [uefi-rs](https://github.com/GabrielMajeri/uefi-rs)
 
-//* Use from external library *//
-use core::mem;
-
 //* Use from local library *//
 use super::Protocol;
-use utility::{Void, PoolPointer, Time, Guid};
+use utility::{Void, PoolPointer, Time, Guid, CStr16};
 use utility::guid::FILE_SYSTEM_GUID;
-use status::{Result, Status};
+use status::{Result, Status, Error};
 
 /// SimpleFileSystem Protocol (With Guid)
 #[repr(C)]
@@ -67,7 +64,7 @@ impl File {
         File(&mut *ptr)
     }
 
-    pub unsafe fn inner_open(&mut self, file_name: &[u16], open_mode: FileOpenMode, attr: FileAttr) -> Result<File> {
+    pub unsafe fn inner_open(&mut self, file_name: &CStr16, open_mode: FileOpenMode, attr: FileAttr) -> Result<File> {
         let mut new_handle = Void::new() as PoolPointer<FileStruct>;
         (self.0.open)(self.0, &mut new_handle, file_name.as_ptr(), open_mode, attr).into_with(|| File::new(new_handle))
     }
@@ -77,9 +74,7 @@ impl File {
     }
 
     pub unsafe fn delete(self) -> Result<()> {
-        let status = (self.0.delete)(self.0).into();
-        mem::forget(self);
-        status
+        (self.0.delete)(self.0).into()
     }
 
     pub unsafe fn inner_read(&mut self, buffer: &mut [u8]) -> Result<usize> {
@@ -101,6 +96,15 @@ impl File {
         (self.0.set_position)(self.0, pos).into()
     }
 
+    /// Returns information about the file, e.g. a `FileInfo` for `FILE_INFO_ID`.
+    ///
+    /// Returns the size of the information in bytes; if `buffer` is too small, the error carries the size required.
+    pub unsafe fn get_info(&mut self, information_type: &Guid, buffer: &mut [u8]) -> Result<usize, Error<usize>> {
+        let mut buffer_size = buffer.len();
+        (self.0.get_info)(self.0, information_type, &mut buffer_size, buffer.as_mut_ptr())
+            .into_with_err(|| buffer_size, |_| buffer_size)
+    }
+
     pub unsafe fn flush(&mut self) -> Result<()> {
         (self.0.flush)(self.0).into()
     }
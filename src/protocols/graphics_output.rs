@@ -8,12 +8,12 @@
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_GRAPHICS_OUTPUT_PROTOCOL
 
 //* Use from external library *//
-use core::{ptr, mem};
+use core::ptr;
 
 //* Use from local library *//
 use super::Protocol;
-use status::{Result, Status, Completion};
-use utility::{Guid, PhysAddress};
+use status::{Result, Status};
+use utility::{Guid, PhysAddress, PageRange};
 use utility::guid::GRAPHICS_OUTPUT_PROTOCOL_GUID;
 
 #[repr(C)]
@@ -31,12 +31,6 @@ impl Protocol for GraphicsOutput {
     const GUID: Guid = GRAPHICS_OUTPUT_PROTOCOL_GUID;
 }
 
-struct ModeIter<'a> {
-    gop: &'a GraphicsOutput,
-    current: u32,
-    max: u32,
-}
-
 impl GraphicsOutput {
     pub unsafe fn query_mode(&self, index: u32) -> Result<SimpleMode> {
         let mut info_size = 0;
@@ -46,7 +40,6 @@ impl GraphicsOutput {
                 let info = &*info;
                 SimpleMode {
                     index,
-                    info_size,
                     info
                 }
             }
@@ -54,7 +47,7 @@ impl GraphicsOutput {
     }
 
     pub fn mode(&self) -> &'static Mode {
-        (self.mode)
+        self.mode
     }
 
     pub unsafe fn set_mode(&mut self, mode: &SimpleMode) -> Result<()> {
@@ -88,40 +81,48 @@ pub struct ModeInfo {
 
 impl ModeInfo {
     pub fn width(&self) -> u32 {
-        (self.horizontal_resolution)
+        self.horizontal_resolution
     }
 
     pub fn height(&self) -> u32 {
-        (self.vertical_resolution)
+        self.vertical_resolution
     }
 
     pub fn pixel_format(&self) -> PixelFormat {
-        (self.pixel_format)
+        self.pixel_format
     }
 
     pub fn pixel_info(&self) -> PixelBitMask {
-        (self.pixel_info)
+        self.pixel_info
     }
 
     pub fn pixels_per_scan_line(&self) -> u32 {
-        (self.pixels_per_scan_line)
+        self.pixels_per_scan_line
     }
 }
 
+/// Referenced by default modes, which describe no actual mode.
+static EMPTY_MODE_INFO: ModeInfo = ModeInfo {
+    version: 0,
+    horizontal_resolution: 0,
+    vertical_resolution: 0,
+    pixel_format: PixelFormat::PixelRedGreenBlueReserved8BitPerColor,
+    pixel_info: PixelBitMask {
+        red_mask: 0,
+        green_mask: 0,
+        blue_mask: 0,
+        _reserved_mask: 0
+    },
+    pixels_per_scan_line: 0
+};
+
 impl Default for ModeInfo {
     fn default() -> Self {
-        Self {
-            version: 0,
-            horizontal_resolution: 0,
-            vertical_resolution: 0,
-            pixel_format: PixelFormat::PixelRedGreenBlueReserved8BitPerColor,
-            pixel_info: PixelBitMask::default(),
-            pixels_per_scan_line: 0
-        }
+        EMPTY_MODE_INFO
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
 #[repr(C)]
 pub struct PixelBitMask {
     pub red_mask: u32,
@@ -130,17 +131,6 @@ pub struct PixelBitMask {
     _reserved_mask: u32
 }
 
-impl Default for PixelBitMask {
-    fn default() -> Self {
-        Self {
-            red_mask: 0,
-            green_mask: 0,
-            blue_mask: 0,
-            _reserved_mask: 0
-        }
-    }
-}
-
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(u32)]
 pub enum PixelFormat {
@@ -161,9 +151,9 @@ pub struct BltPixel {
 impl BltPixel {
     pub fn new(red: u8, green: u8, blue: u8) -> Self{
         Self {
-            blue: blue,
-            green: green,
-            red: red,
+            blue,
+            green,
+            red,
             _reserved: 0
         }
     }
@@ -183,13 +173,12 @@ pub enum BltOperation {
 
 pub struct SimpleMode {
     index: u32,
-    info_size: usize,
     info: &'static ModeInfo,
 }
 
 impl SimpleMode {
     pub fn info(&self) -> &'static ModeInfo {
-        (self.info)
+        self.info
     }
 
     pub fn width(&self) -> u32 {
@@ -203,7 +192,10 @@ impl SimpleMode {
 
 impl Default for SimpleMode {
     fn default() -> Self {
-        unsafe { mem::zeroed() }
+        Self {
+            index: 0,
+            info: &EMPTY_MODE_INFO
+        }
     }
 }
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -219,15 +211,30 @@ pub struct Mode {
 
 impl Mode {
     pub fn max_mode(&self) -> u32 {
-        (self.max_mode)
+        self.max_mode
     }
 
     pub fn info(&self) -> &'static ModeInfo {
-        (self.info)
+        self.info
     }
 
     pub fn base(&self) -> PhysAddress {
-        (self.frame_buffer_base)
+        self.frame_buffer_base
+    }
+
+    /// Size of the frame buffer in bytes.
+    pub fn size(&self) -> usize {
+        self.frame_buffer_size
+    }
+
+    /// Returns a pointer to the frame buffer, which is identity mapped during boot services.
+    pub fn frame_buffer(&self) -> *mut u8 {
+        self.frame_buffer_base.as_mut_ptr()
+    }
+
+    /// Returns the pages spanned by the frame buffer, e.g. to map them after exiting boot services.
+    pub fn frame_buffer_pages(&self) -> PageRange {
+        PageRange::covering(self.frame_buffer_base, self.frame_buffer_size as u64)
     }
 }
 
@@ -236,7 +243,7 @@ impl Default for Mode {
         Self {
             max_mode: 0,
             mode: 0,
-            info: unsafe { &*(0 as *const ModeInfo) },
+            info: &EMPTY_MODE_INFO,
             size_of_info: 0,
             frame_buffer_base: PhysAddress::new(0),
             frame_buffer_size: 0
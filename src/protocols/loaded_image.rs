@@ -0,0 +1,139 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Specification, 9.1 EFI Loaded Image Protocol
+
+//* Use from external library *//
+use core::{mem, slice};
+use core::ops::Deref;
+
+//* Use from local library *//
+use super::Protocol;
+use super::device_path::DevicePath;
+use status::Status;
+use tables::SystemTable;
+use utility::{Void, Guid, Handle, MemoryType, CStr16};
+use utility::guid::{LOADED_IMAGE_PROTOCOL_GUID, LOADED_IMAGE_DEVICE_PATH_PROTOCOL_GUID};
+
+/// Installed on the handle of every loaded image, describes where it came from and where it lives.
+#[repr(C)]
+pub struct LoadedImage {
+    pub revision: u32,
+    parent_handle: Option<Handle>,
+    system_table: *const SystemTable,
+
+    /* --- Source location of the image --- */
+    device_handle: Option<Handle>,
+    file_path: *const DevicePath,
+    reserved: *const Void,
+
+    /* --- Image's load options --- */
+    load_options_size: u32,
+    load_options: *const u8,
+
+    /* --- Location where image was loaded --- */
+    image_base: *const u8,
+    image_size: u64,
+    image_code_type: MemoryType,
+    image_data_type: MemoryType,
+    unload: Option<efi_fcn! { fn(image_handle: Handle) -> Status }>
+}
+
+impl Protocol for LoadedImage {
+    const GUID: Guid = LOADED_IMAGE_PROTOCOL_GUID;
+}
+
+impl LoadedImage {
+    /// Returns the image that loaded this one, `None` for images loaded by the firmware itself.
+    pub fn parent_handle(&self) -> Option<Handle> {
+        self.parent_handle
+    }
+
+    /// Returns the device the image was loaded from, e.g. to open its `SimpleFileSystem`.
+    ///
+    /// `None` if the image was loaded from a buffer.
+    pub fn device_handle(&self) -> Option<Handle> {
+        self.device_handle
+    }
+
+    /// Returns the path of the image file relative to `device_handle`.
+    pub fn file_path(&self) -> Option<&DevicePath> {
+        unsafe { self.file_path.as_ref() }
+    }
+
+    /// Returns the raw load options, which the boot manager fills with the optional data of the load option.
+    pub fn load_options(&self) -> &[u8] {
+        if self.load_options.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.load_options, self.load_options_size as usize) }
+    }
+
+    /// Returns the load options as a UCS-2 string, the usual format of a command line.
+    ///
+    /// Returns `None` if they are empty or not a null-terminated UCS-2 string.
+    pub fn load_options_cstr16(&self) -> Option<&CStr16> {
+        let options = self.load_options();
+        if options.is_empty() || options.as_ptr() as usize % mem::align_of::<u16>() != 0 {
+            return None;
+        }
+        let codes = unsafe { slice::from_raw_parts(options.as_ptr() as *const u16, options.len() / 2) };
+        CStr16::from_u16_until_nul(codes).ok()
+    }
+
+    /// Sets the load options of an image before it is started with `start_image`.
+    ///
+    /// `options` must stay valid until the image no longer uses them.
+    pub unsafe fn set_load_options(&mut self, options: &[u8]) {
+        self.load_options = options.as_ptr();
+        self.load_options_size = options.len() as u32;
+    }
+
+    /// Sets the load options to a UCS-2 command line, see `set_load_options`.
+    pub unsafe fn set_load_options_cstr16(&mut self, options: &CStr16) {
+        self.load_options = options.as_ptr() as *const u8;
+        self.load_options_size = options.size_in_bytes() as u32;
+    }
+
+    /// Returns the address the image was loaded at.
+    pub fn image_base(&self) -> *const u8 {
+        self.image_base
+    }
+
+    /// Returns the size of the loaded image in bytes.
+    pub fn image_size(&self) -> u64 {
+        self.image_size
+    }
+
+    /// Returns the memory type of the code sections.
+    pub fn image_code_type(&self) -> MemoryType {
+        self.image_code_type
+    }
+
+    /// Returns the memory type of the data sections.
+    pub fn image_data_type(&self) -> MemoryType {
+        self.image_data_type
+    }
+}
+
+/// The full device path of the image file, installed next to `LoadedImage` unless the image was loaded from a buffer.
+///
+/// Unlike `LoadedImage::file_path`, it includes the path of the device.
+#[repr(transparent)]
+pub struct LoadedImageDevicePath(DevicePath);
+
+impl Protocol for LoadedImageDevicePath {
+    const GUID: Guid = LOADED_IMAGE_DEVICE_PATH_PROTOCOL_GUID;
+}
+
+impl Deref for LoadedImageDevicePath {
+    type Target = DevicePath;
+
+    fn deref(&self) -> &DevicePath {
+        &self.0
+    }
+}
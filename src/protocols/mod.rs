@@ -9,6 +9,8 @@ pub mod console;
 pub mod device_path;
 pub mod graphics_output;
 pub mod file_system;
+pub mod loaded_image;
+pub mod shell_parameters;
 
 //* Use from local library *//
 use utility::Guid;
@@ -0,0 +1,105 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Shell Specification, 2.3 Shell Parameters Protocol
+
+//* Use from external library *//
+use core::ffi::c_void;
+use core::ptr::NonNull;
+
+//* Use from local library *//
+use super::Protocol;
+use utility::{Guid, CStr16};
+use utility::guid::SHELL_PARAMETERS_GUID;
+use utility::string::FromSliceWithNulError;
+
+/// Installed by the UEFI Shell on the handle of the images it starts.
+#[repr(C)]
+pub struct ShellParameters {
+    argv: *const *const u16,
+    argc: usize,
+    std_in: Option<ShellFileHandle>,
+    std_out: Option<ShellFileHandle>,
+    std_err: Option<ShellFileHandle>
+}
+
+/// A file opened by the shell, e.g. a redirection target.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+pub struct ShellFileHandle(NonNull<c_void>);
+
+impl ShellFileHandle {
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+impl Protocol for ShellParameters {
+    const GUID: Guid = SHELL_PARAMETERS_GUID;
+}
+
+impl ShellParameters {
+    /// Returns the number of arguments, including the name of the image.
+    pub fn argc(&self) -> usize {
+        self.argc
+    }
+
+    /// Returns an argument, the first one being the name of the image as typed.
+    ///
+    /// Returns `None` if `index` is out of range and an error if the argument is missing or not valid UCS-2.
+    pub fn arg(&self, index: usize) -> Option<Result<&CStr16, FromSliceWithNulError>> {
+        if index >= self.argc || self.argv.is_null() {
+            return None;
+        }
+        let arg = unsafe { *self.argv.add(index) };
+        if arg.is_null() {
+            return Some(Err(FromSliceWithNulError::NotNulTerminated));
+        }
+        Some(unsafe { CStr16::from_ptr(arg) })
+    }
+
+    /// Returns an iterator over the arguments, yielding an error for each invalid one.
+    pub fn args(&self) -> Args<'_> {
+        Args {
+            parameters: self,
+            index: 0
+        }
+    }
+
+    pub fn std_in(&self) -> Option<ShellFileHandle> {
+        self.std_in
+    }
+
+    pub fn std_out(&self) -> Option<ShellFileHandle> {
+        self.std_out
+    }
+
+    pub fn std_err(&self) -> Option<ShellFileHandle> {
+        self.std_err
+    }
+}
+
+/// Iterator over the arguments passed by the shell.
+pub struct Args<'a> {
+    parameters: &'a ShellParameters,
+    index: usize
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = Result<&'a CStr16, FromSliceWithNulError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let arg = self.parameters.arg(self.index)?;
+        self.index += 1;
+        Some(arg)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.parameters.argc().saturating_sub(self.index);
+        (0, Some(remaining))
+    }
+}
@@ -0,0 +1,50 @@
+//* Use from external library *//
+use core::fmt;
+
+//* Use from local library *//
+use super::Status;
+
+/// An UEFI error status along with data describing it,
+/// such as the required buffer size of a `Status::BufferTooSmall`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Error<Data = ()> {
+    status: Status,
+    data: Data,
+}
+
+impl<Data> Error<Data> {
+    pub fn new(status: Status, data: Data) -> Self {
+        Error { status, data }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+
+    /// Split the error into a (status, data) pair
+    pub fn split(self) -> (Status, Data) {
+        (self.status, self.data)
+    }
+}
+
+impl From<Status> for Error<()> {
+    fn from(status: Status) -> Self {
+        Error::new(status, ())
+    }
+}
+
+impl<Data> From<Error<Data>> for Status {
+    fn from(error: Error<Data>) -> Self {
+        error.status
+    }
+}
+
+impl<Data> fmt::Display for Error<Data> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.status, f)
+    }
+}
@@ -2,12 +2,15 @@
 
 pub mod status;
 pub mod completion;
+pub mod error;
 
 //* Use from local library *//
 pub use self::status::Status;
 pub use self::completion::Completion;
+pub use self::error::Error;
 
-pub type Result<T> = core::result::Result<Completion<T>, Status>;
+/// Result of an UEFI operation, errors are a `Status` unless they carry extra data in an `Error`.
+pub type Result<T, E = Status> = core::result::Result<Completion<T>, E>;
 
 /// Extension trait for Result which helps dealing with UEFI's warnings
 pub trait ResultExt<T> {
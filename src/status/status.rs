@@ -1,57 +1,153 @@
 //! Some code was borrowed from [uefi-rs](https://github.com/GabrielMajeri/uefi-rs/blob/master/src/error/status.rs)
 
 //* Use from external library *//
-use core::ops;
+use core::fmt;
 
 //* Use from local library *//
-use super::{Result, Completion};
+use super::{Result, Completion, Error};
 
 //* Constants & Types *//
 const HIGHEST_BIT_SET: usize = !((!0_usize) >> 1);
+const SECOND_HIGHEST_BIT_SET: usize = HIGHEST_BIT_SET >> 1;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-#[repr(usize)]
-pub enum Status {
-    Success,
-    LoadError,
-    InvalidParameter,
-    Unsupported,
-    BadBufferSize,
-    BufferTooSmall,
-    NotReady,
-    DeviceError,
-    WriteProtected,
-    OutOfResources,
-    VolumeCorrupted,
-    VolumeFull,
-    NoMedia,
-    MediaChanged,
-    NotFound,
-    AccessDenied,
-    NoResponse,
-    NoMapping,
-    Timeout,
-    NotStarted,
-    AlreadyStarted,
-    Aborted,
-    IcmpError,
-    TftpError,
-    ProtocolError,
-    IncompatibleVersion,
-    SecurityViolation,
-    CrcError,
-    EndOfMedia,
-    Error29,
-    Error30,
-    EndOfFile,
-    InvalidLanguage,
-    CompromisedData,
-    Error34,
-    HttpError,
-    Unknown
+/// An UEFI status code.
+///
+/// Error codes have the highest bit set, any other non-zero value is a warning.
+/// Codes with the second highest bit set are reserved for OEMs; unknown codes are preserved as is.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+pub struct Status(usize);
+
+macro_rules! status_codes {
+    ($(#[doc = $description:expr] $name:ident = $value:expr,)*) => {
+        #[allow(non_upper_case_globals)]
+        impl Status {
+            $(
+                #[doc = $description]
+                pub const $name: Status = Status($value);
+            )*
+
+            /// Returns the name of a status code defined by the specification.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(Status::$name => Some(stringify!($name)),)*
+                    _ => None
+                }
+            }
+
+            /// Returns the description of a status code defined by the specification,
+            /// the documentation of its constant without the final period.
+            pub fn description(self) -> Option<&'static str> {
+                match self {
+                    $(Status::$name => Some($description.trim().trim_end_matches('.')),)*
+                    _ => None
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    /// The operation completed successfully.
+    Success = 0,
+
+    /* --- Warnings --- */
+    /// The string contained characters that could not be rendered and were skipped.
+    WarnUnknownGlyph = 1,
+    /// The handle was closed, but the file was not deleted.
+    WarnDeleteFailure = 2,
+    /// The handle was closed, but the data to the file was not flushed properly.
+    WarnWriteFailure = 3,
+    /// The resulting buffer was too small, and the data was truncated.
+    WarnBufferTooSmall = 4,
+    /// The data has not been updated within the timeframe set by local policy.
+    WarnStaleData = 5,
+    /// The resulting buffer contains UEFI-compliant file system.
+    WarnFileSystem = 6,
+    /// The operation will be processed across a system reset.
+    WarnResetRequired = 7,
+
+    /* --- Errors --- */
+    /// The image failed to load.
+    LoadError = HIGHEST_BIT_SET | 1,
+    /// A parameter was incorrect.
+    InvalidParameter = HIGHEST_BIT_SET | 2,
+    /// The operation is not supported.
+    Unsupported = HIGHEST_BIT_SET | 3,
+    /// The buffer was not the proper size for the request.
+    BadBufferSize = HIGHEST_BIT_SET | 4,
+    /// The buffer is not large enough to hold the requested data.
+    BufferTooSmall = HIGHEST_BIT_SET | 5,
+    /// There is no data pending upon return.
+    NotReady = HIGHEST_BIT_SET | 6,
+    /// The physical device reported an error while attempting the operation.
+    DeviceError = HIGHEST_BIT_SET | 7,
+    /// The device cannot be written to.
+    WriteProtected = HIGHEST_BIT_SET | 8,
+    /// A resource has run out.
+    OutOfResources = HIGHEST_BIT_SET | 9,
+    /// An inconsistency was detected on the file system.
+    VolumeCorrupted = HIGHEST_BIT_SET | 10,
+    /// There is no more space on the file system.
+    VolumeFull = HIGHEST_BIT_SET | 11,
+    /// The device does not contain any medium to perform the operation.
+    NoMedia = HIGHEST_BIT_SET | 12,
+    /// The medium in the device has changed since the last access.
+    MediaChanged = HIGHEST_BIT_SET | 13,
+    /// The item was not found.
+    NotFound = HIGHEST_BIT_SET | 14,
+    /// Access was denied.
+    AccessDenied = HIGHEST_BIT_SET | 15,
+    /// The server was not found or did not respond to the request.
+    NoResponse = HIGHEST_BIT_SET | 16,
+    /// A mapping to a device does not exist.
+    NoMapping = HIGHEST_BIT_SET | 17,
+    /// The timeout time expired.
+    Timeout = HIGHEST_BIT_SET | 18,
+    /// The protocol has not been started.
+    NotStarted = HIGHEST_BIT_SET | 19,
+    /// The protocol has already been started.
+    AlreadyStarted = HIGHEST_BIT_SET | 20,
+    /// The operation was aborted.
+    Aborted = HIGHEST_BIT_SET | 21,
+    /// An ICMP error occurred during the network operation.
+    IcmpError = HIGHEST_BIT_SET | 22,
+    /// A TFTP error occurred during the network operation.
+    TftpError = HIGHEST_BIT_SET | 23,
+    /// A protocol error occurred during the network operation.
+    ProtocolError = HIGHEST_BIT_SET | 24,
+    /// The function encountered an internal version that was incompatible with a version requested by the caller.
+    IncompatibleVersion = HIGHEST_BIT_SET | 25,
+    /// The function was not performed due to a security violation.
+    SecurityViolation = HIGHEST_BIT_SET | 26,
+    /// A CRC error was detected.
+    CrcError = HIGHEST_BIT_SET | 27,
+    /// Beginning or end of media was reached.
+    EndOfMedia = HIGHEST_BIT_SET | 28,
+    /// The end of the file was reached.
+    EndOfFile = HIGHEST_BIT_SET | 31,
+    /// The language specified was invalid.
+    InvalidLanguage = HIGHEST_BIT_SET | 32,
+    /// The security status of the data is unknown or compromised.
+    CompromisedData = HIGHEST_BIT_SET | 33,
+    /// There is an address conflict during address allocation.
+    IpAddressConflict = HIGHEST_BIT_SET | 34,
+    /// A HTTP error occurred during the network operation.
+    HttpError = HIGHEST_BIT_SET | 35,
 }
 
 impl Status {
+    /// Wraps a raw status code returned by the firmware.
+    #[inline]
+    pub const fn from_raw(value: usize) -> Self {
+        Status(value)
+    }
+
+    #[inline]
+    pub fn raw(self) -> usize {
+        self.0
+    }
+
     #[inline]
     pub fn is_success(self) -> bool {
         self == Status::Success
@@ -59,12 +155,18 @@ impl Status {
 
     #[inline]
     pub fn is_warning(self) -> bool {
-        (self as usize) & HIGHEST_BIT_SET == 0
+        self.0 != 0 && self.0 & HIGHEST_BIT_SET == 0
     }
 
     #[inline]
     pub fn is_error(self) -> bool {
-        (self as usize) & HIGHEST_BIT_SET != 0
+        self.0 & HIGHEST_BIT_SET != 0
+    }
+
+    /// Returns whether the code is in the range reserved for OEMs.
+    #[inline]
+    pub fn is_oem(self) -> bool {
+        self.0 & SECOND_HIGHEST_BIT_SET != 0
     }
 
     #[inline]
@@ -80,28 +182,66 @@ impl Status {
             Err(self)
         }
     }
-}
 
-impl Into<Result<()>> for Status {
+    /// Like `into_with`, but attaches data produced by `err` to errors.
     #[inline]
-    fn into(self) -> Result<()> {
-        self.into_with(|| ())
+    pub fn into_with_err<T, D, F, G>(self, ok: F, err: G) -> Result<T, Error<D>>
+    where
+        F: FnOnce() -> T,
+        G: FnOnce(Status) -> D,
+    {
+        if self.is_success() {
+            Ok(Completion::Success(ok()))
+        } else if self.is_warning() {
+            Ok(Completion::Warning(ok(), self))
+        } else {
+            Err(Error::new(self, err(self)))
+        }
     }
 }
 
-impl ops::Try for Status {
-    type Ok = Completion<()>;
-    type Error = Status;
+impl fmt::Debug for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "Status({:#x})", self.0)
+        }
+    }
+}
 
-    fn into_result(self) -> Result<()> {
-        self.into()
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.name(), self.description()) {
+            (Some(name), Some(description)) => write!(f, "{} ({})", description, name),
+            _ => {
+                let kind = if self.is_error() { "error" } else { "warning" };
+                let code = self.0 & !(HIGHEST_BIT_SET | SECOND_HIGHEST_BIT_SET);
+                if self.is_oem() {
+                    write!(f, "OEM {} {:#x}", kind, code)
+                } else {
+                    write!(f, "Unknown {} {:#x}", kind, code)
+                }
+            }
+        }
     }
+}
 
-    fn from_error(error: Self::Error) -> Self {
-        error
+impl From<Status> for Result<()> {
+    #[inline]
+    fn from(status: Status) -> Self {
+        status.into_with(|| ())
     }
+}
 
-    fn from_ok(_: Self::Ok) -> Self {
-        Status::Success
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(Status::IpAddressConflict.description(), Some("There is an address conflict during address allocation"));
+        assert_eq!(Status::NotFound.to_string(), "The item was not found (NotFound)");
+        assert_eq!(Status::from_raw(HIGHEST_BIT_SET | 29).description(), None);
     }
-}
\ No newline at end of file
+}
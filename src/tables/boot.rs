@@ -11,20 +11,23 @@
 
 //* Use from external library *//
 use core::{mem, ptr};
+use core::mem::MaybeUninit;
 
 //* Use from local library *//
 use super::TableHeader;
 use protocols::Protocol;
 use protocols::device_path::DevicePath;
-use status::{Result, Status};
+use status::{Result, Status, Error};
 use utility::{Void,
               PoolPointer,
               EventNotifyFcn,
               Event,
               Guid,
               Handle,
-              PhysAddress, 
-              MemoryType, 
+              PhysAddress,
+              Page,
+              PageRange,
+              MemoryType,
               MemoryDescriptor};
 
 #[repr(C)]
@@ -39,19 +42,19 @@ pub struct BootServices {
 
     /* --- Memory Allocate --- */
     /// Allocates memory pages from the system
-    pub allocate_pages: efi_fcn! { fn(alloc_type: AllocType, mem_type: MemoryType, count: usize, addr: &mut PoolPointer<Void>) -> Status },
+    pub allocate_pages: efi_fcn! { fn(alloc_type: AllocType, mem_type: MemoryType, count: usize, addr: &mut PhysAddress) -> Status },
     /// Frees memory pages
     pub free_pages: efi_fcn! { fn(addr: PhysAddress, count: usize) -> Status },
     /// Returns the current memory map
-    pub get_memory_map: efi_fcn! { fn(size: &mut usize, map: &mut MemoryDescriptor, key: &mut usize, desc_size: &mut usize, desc_version: &mut usize) -> Status },
+    pub get_memory_map: efi_fcn! { fn(size: &mut usize, map: *mut MemoryDescriptor, key: &mut usize, desc_size: &mut usize, desc_version: &mut u32) -> Status },
     /// Allocates pool memory
-    pub allocate_pool: efi_fcn! { fn(pool_type: MemoryType, size: usize, addr: &mut PhysAddress) -> Status },
+    pub allocate_pool: efi_fcn! { fn(pool_type: MemoryType, size: usize, buffer: &mut PoolPointer<u8>) -> Status },
     /// Returns pool memory to the system
-    pub free_pool: efi_fcn! { fn(addr: &mut PhysAddress) -> Status },
+    pub free_pool: efi_fcn! { fn(buffer: PoolPointer<u8>) -> Status },
     
     /* --- Event & Timer --- */
     /// Creates an event
-    pub create_event: efi_fcn! { fn(event_type: u32, notify_tpl: Tpl, notify_function: Option<EventNotifyFcn>, notify_context: Option<PoolPointer<Void>>, event: &mut Event) -> Status },
+    pub create_event: efi_fcn! { fn(event_type: u32, notify_tpl: Tpl, notify_function: Option<EventNotifyFcn>, notify_context: PoolPointer<Void>, event: &mut Option<Event>) -> Status },
     /// Sets the type of timer and the trigger time for a timer event
     pub set_timer: efi_fcn! { fn(event: Event, delay_type: TimerDelay, trigger_time: u64) -> Status },
     /// Stops execution until an event is signaled
@@ -62,25 +65,27 @@ pub struct BootServices {
     pub check_event: efi_fcn! { fn(event: Event) -> Status },
 
     /* --- Protocol --- */
-    pub install_protocol_interface: efi_fcn! { fn(handle: &mut Handle, protocol: &Guid, interface_type: InterfaceType, interface: PoolPointer<Void>) -> Status },
+    pub install_protocol_interface: efi_fcn! { fn(handle: &mut Option<Handle>, protocol: &Guid, interface_type: InterfaceType, interface: PoolPointer<Void>) -> Status },
     pub reinstall_protocol_interface: efi_fcn! { fn(handle: Handle, protocol: &Guid, old_interface: PoolPointer<Void>, new_interface: PoolPointer<Void>) -> Status },
     pub uninstall_protocol_interface: efi_fcn! { fn(handle: Handle, protocol: &Guid, interface: PoolPointer<Void>) -> Status },
     /// Queries a handle to determine if it supports a specified protocol
-    pub handle_protocol: efi_fcn! { fn(handle: Handle, protocol: &Guid, interface: &mut usize) -> Status },
+    pub handle_protocol: efi_fcn! { fn(handle: Handle, protocol: &Guid, interface: &mut *mut Void) -> Status },
     _reserved: usize,
     /// Creates an event that is to be signaled whenever an interface is installed for a specified protocol
-    pub register_protocol_notify: efi_fcn! { fn(protocol: &Guid, event: Event, registration: PoolPointer<Void>) -> Status },
+    pub register_protocol_notify: efi_fcn! { fn(protocol: &Guid, event: Event, registration: &mut *mut Void) -> Status },
     /// Returns an array of handles that support a specified protocol
-    pub locate_handle: efi_fcn! { fn(search_type: i32, protocol: &Guid, search_key: PoolPointer<Void>, buffer_size: &mut usize, buffer: *mut Handle) -> Status },
-    pub locate_device_path: usize,
-    pub install_configuration_table: usize,
+    pub locate_handle: efi_fcn! { fn(search_type: i32, protocol: *const Guid, search_key: *const Void, buffer_size: &mut usize, buffer: *mut Handle) -> Status },
+    /// Locates the handle to a device on the device path that supports the specified protocol
+    pub locate_device_path: efi_fcn! { fn(protocol: &Guid, device_path: &mut *const DevicePath, device: &mut Option<Handle>) -> Status },
+    /// Adds, updates, or removes a configuration table entry from the EFI System Table
+    pub install_configuration_table: efi_fcn! { fn(guid: &Guid, table: *const Void) -> Status },
 
     /* --- Image services --- */
     /// Loads an EFI image into memory
-    pub load_image: efi_fcn! { fn(boot_policy: bool, parent_image_handle: Handle, device_path: &mut DevicePath, source_buffer: PoolPointer<Void>, source_size: usize, image_handle: &mut Handle) -> Status },
+    pub load_image: efi_fcn! { fn(boot_policy: bool, parent_image_handle: Handle, device_path: Option<&DevicePath>, source_buffer: *const Void, source_size: usize, image_handle: &mut Option<Handle>) -> Status },
     /// Transfers control to a loaded image’s entry point
-    pub start_image: efi_fcn! { fn(image_handle: Handle, exit_data_size: usize, exit_data: &mut PoolPointer<u16>) -> Status },
-    pub exit: efi_fcn! { fn(image_handle: Handle, exit_status: Status, exit_data_size: usize, exit_data: &u16) -> Status },
+    pub start_image: efi_fcn! { fn(image_handle: Handle, exit_data_size: &mut usize, exit_data: &mut PoolPointer<u16>) -> Status },
+    pub exit: efi_fcn! { fn(image_handle: Handle, exit_status: Status, exit_data_size: usize, exit_data: *const u16) -> Status },
     pub unload_image: efi_fcn! { fn(image_handle: Handle) -> Status },
     pub exit_boot_services: efi_fcn! { fn(image_handle: Handle, map_key: usize) -> Status },
 
@@ -89,22 +94,24 @@ pub struct BootServices {
     pub get_next_monotonic_count: efi_fcn! { fn(count: &mut u64) -> Status },
     /// Induces a fine-grained stall
     pub stall: efi_fcn! { fn(microseconds: usize) -> Status },
-    pub set_watchdog_timer: efi_fcn! { fn(timeout: usize, watchdog_code: u64, data_size: usize, watchdog_data: &u16) -> Status },
+    pub set_watchdog_timer: efi_fcn! { fn(timeout: usize, watchdog_code: u64, data_size: usize, watchdog_data: *const u16) -> Status },
     
     /* --- Driver support services --- */
     /// Connects one or more drivers to a controller
-    pub connect_controller: efi_fcn! { fn(controller_handle: Handle, driver_image_handle: &Handle, remaining_device_path: &DevicePath, recursive: bool) -> Status },
+    pub connect_controller: efi_fcn! { fn(controller_handle: Handle, driver_image_handle: *const Option<Handle>, remaining_device_path: Option<&DevicePath>, recursive: bool) -> Status },
     /// Disconnects one or more drivers from a controller
-    pub disconnect_controller: efi_fcn! { fn(controller_handle: Handle, driver_image_handle: Handle, child_handle: Handle) -> Status },
+    pub disconnect_controller: efi_fcn! { fn(controller_handle: Handle, driver_image_handle: Option<Handle>, child_handle: Option<Handle>) -> Status },
     
     /* --- Open and Close Protocol Services --- */
-    pub open_protocol: efi_fcn! { fn(handle: Handle, protocol: &Guid, interface: PoolPointer<Void>, agent_handle: Handle, controller_handle: Handle, attributes: ProtocolAttribute) -> Status },
-    pub close_protocol: efi_fcn! { fn(handle: Handle, protocol: &Guid, agent_handle: Handle, controller_handle: Handle) -> Status },
+    pub open_protocol: efi_fcn! { fn(handle: Handle, protocol: &Guid, interface: *mut *mut Void, agent_handle: Handle, controller_handle: Option<Handle>, attributes: ProtocolAttribute) -> Status },
+    pub close_protocol: efi_fcn! { fn(handle: Handle, protocol: &Guid, agent_handle: Handle, controller_handle: Option<Handle>) -> Status },
 
     /* --- Library Services --- */
-    pub protocol_per_handle: efi_fcn! { fn(handle: Handle, protocol_buffer: &mut Guid, protocol_buffer_count: &mut usize) -> Status },
-    pub locate_handle_buffer: efi_fcn! { fn(search_type: i32, protocol: &Guid, search_key: *const Void, no_handles: &mut *mut usize, buffer: &mut *mut Handle) -> Status },
-    pub locate_protocol: efi_fcn! { fn(protocol: *const Guid, registration: *const Void, interface: *mut Void) -> Status },
+    pub protocol_per_handle: efi_fcn! { fn(handle: Handle, protocol_buffer: &mut *mut *const Guid, protocol_buffer_count: &mut usize) -> Status },
+    pub locate_handle_buffer: efi_fcn! { fn(search_type: i32, protocol: *const Guid, search_key: *const Void, no_handles: &mut usize, buffer: &mut *mut Handle) -> Status },
+    pub locate_protocol: efi_fcn! { fn(protocol: &Guid, registration: *const Void, interface: &mut *mut Void) -> Status },
+    // The multiple protocol interface services are variadic, which `efiapi` function pointers
+    // cannot express on the supported Rust versions, so they are left without parameters.
     pub install_multiple_protocol_interfaces: efi_fcn! { fn() -> Status },
     pub uninstall_multiple_protocol_interfaces: efi_fcn! { fn() -> Status },
 
@@ -112,9 +119,11 @@ pub struct BootServices {
     pub calculate_crc32: efi_fcn! { fn(data: *const Void, data_size: usize, crc32: &mut u32) -> Status },
     
     /* --- Other Services --- */
-    pub copy_mem: efi_fcn! { fn(destination: *const Void, source: *const Void, lengh: usize) -> Status },
-    pub set_mem: efi_fcn! { fn(buffer: *const Void, size: usize, value: u8) -> Status },
-    pub create_event_ex: efi_fcn! { fn(ty: u32, notify_tpl: Tpl, notify_function: Option<EventNotifyFcn>, notify_content: Option<*const Void>, event_group: Option<*const Guid>, event: *mut Event) -> Status }
+    /// Copies a buffer to another, the buffers may overlap
+    pub copy_mem: efi_fcn! { fn(destination: *mut Void, source: *const Void, length: usize) -> () },
+    /// Fills a buffer with a value
+    pub set_mem: efi_fcn! { fn(buffer: *mut Void, size: usize, value: u8) -> () },
+    pub create_event_ex: efi_fcn! { fn(ty: u32, notify_tpl: Tpl, notify_function: Option<EventNotifyFcn>, notify_content: *const Void, event_group: *const Guid, event: &mut Option<Event>) -> Status }
 }
 
 impl BootServices {
@@ -130,58 +139,167 @@ impl BootServices {
     }
 
     /* --- Memory Allocate --- */
-    pub unsafe fn allocate_pool(&self, pool_type: MemoryType, size: usize) -> Result<PhysAddress> {
-        let mut buffer = PhysAddress::new(0);
+    /// Allocates `count` pages of memory.
+    ///
+    /// `address` is the highest acceptable address for `AllocType::MaxAddress`
+    /// and the address to allocate at for `AllocType::Address`, it is ignored otherwise.
+    pub unsafe fn allocate_pages(&self, alloc_type: AllocType, memory_type: MemoryType, count: usize, address: PhysAddress) -> Result<PageRange> {
+        let mut address = address;
+        (self.allocate_pages)(alloc_type, memory_type, count, &mut address)
+            .into_with(|| PageRange::new(Page::containing(address), count as u64))
+    }
+
+    /// Frees pages allocated by `allocate_pages`.
+    pub unsafe fn free_pages(&self, pages: PageRange) -> Result<()> {
+        (self.free_pages)(pages.start_address(), pages.count() as usize).into()
+    }
+
+    /// Allocates pool memory, which is 8-byte aligned.
+    pub unsafe fn allocate_pool(&self, pool_type: MemoryType, size: usize) -> Result<PoolPointer<u8>> {
+        let mut buffer = ptr::null_mut();
         (self.allocate_pool)(pool_type, size, &mut buffer).into_with(|| buffer)
     }
 
-    pub unsafe fn free_pool(&self, address: PhysAddress) -> Result<()> {
-        let mut addr = address.clone();
-        (self.free_pool)(&mut addr).into()
+    /// Returns pool memory allocated by `allocate_pool` to the system.
+    pub unsafe fn free_pool(&self, buffer: PoolPointer<u8>) -> Result<()> {
+        (self.free_pool)(buffer).into()
+    }
+
+    /* --- Event & Timer --- */
+    /// Creates an event
+    pub unsafe fn create_event(&self, event_type: EventType, notify_tpl: Tpl, notify_function: Option<EventNotifyFcn>, notify_context: Option<PoolPointer<Void>>) -> Result<Event> {
+        let mut event = None;
+        let status = (self.create_event)(event_type.bits(), notify_tpl, notify_function, notify_context.unwrap_or(ptr::null_mut()), &mut event);
+        match event {
+            Some(event) if !status.is_error() => status.into_with(|| event),
+            // A successful call always returns an event.
+            _ if !status.is_error() => Err(Status::NotFound),
+            _ => Err(status)
+        }
+    }
+
+    /// Sets the type of timer and the trigger time for a timer event, in 100ns units.
+    pub unsafe fn set_timer(&self, event: &Event, delay_type: TimerDelay, trigger_time: u64) -> Result<()> {
+        (self.set_timer)(event.unsafe_clone(), delay_type, trigger_time).into()
+    }
+
+    /// Stops execution until one of the events is signaled, returns its index.
+    pub unsafe fn wait_for_event(&self, events: &mut [Event]) -> Result<usize> {
+        let mut index = 0;
+        (self.wait_for_event)(events.len(), events.as_mut_ptr(), &mut index).into_with(|| index)
+    }
+
+    /// Signals an event.
+    pub unsafe fn signal_event(&self, event: &Event) -> Result<()> {
+        (self.signal_event)(event.unsafe_clone()).into()
+    }
+
+    /// Closes an event, other references to it must not be used afterwards.
+    pub unsafe fn close_event(&self, event: Event) -> Result<()> {
+        (self.close_event)(event).into()
+    }
+
+    /// Returns whether an event is in the signaled state, which is cleared.
+    pub unsafe fn check_event(&self, event: &Event) -> Result<bool> {
+        match (self.check_event)(event.unsafe_clone()) {
+            Status::NotReady => Ok(false.into()),
+            status => status.into_with(|| true)
+        }
     }
 
     /* --- Protocol --- */
     /// Queries a handle to determine if it supports a specified protocol
     pub unsafe fn handle_protocol<P: Protocol>(&self, handle: Handle) -> Option<ptr::NonNull<P>> {
-        let mut ptr = 0usize;
+        let mut ptr = ptr::null_mut();
         match (self.handle_protocol)(handle, &P::GUID, &mut ptr) {
             Status::Success => ptr::NonNull::new(ptr as *mut P),
             _ => None,
         }
     }
 
+    /// Returns the handle of the device closest to the end of `device_path` which supports `P`.
+    ///
+    /// On success, `device_path` is advanced to the remaining part of the path.
+    pub unsafe fn locate_device_path<P: Protocol>(&self, device_path: &mut &DevicePath) -> Result<Handle> {
+        let mut path = *device_path as *const DevicePath;
+        let mut device = None;
+        let status = (self.locate_device_path)(&P::GUID, &mut path, &mut device);
+        match device {
+            Some(device) if !status.is_error() => {
+                *device_path = &*path;
+                status.into_with(|| device)
+            },
+            // A successful call always returns a device.
+            _ if !status.is_error() => Err(Status::NotFound),
+            _ => Err(status)
+        }
+    }
+
     /// Returns an array of handles that support a specified protocol
-    pub unsafe fn locate_handle(&self, search_type: LocateSearchType, output: Option<&mut [Handle]>) -> Result<usize> {
+    ///
+    /// If `output` is `None`, only the number of handles is queried.
+    /// If it is too small, the error carries the number of handles required.
+    /// On success, the first handles of `output` are initialized.
+    pub unsafe fn locate_handle(&self, search_type: LocateSearchType, output: Option<&mut [MaybeUninit<Handle>]>) -> Result<usize, Error<usize>> {
         let handle_size = mem::size_of::<Handle>();
 
         const NULL_BUFFER: *mut Handle = ptr::null_mut();
 
         let (mut buffer_size, buffer) = match output {
-            Some(buffer) => (buffer.len() * handle_size, buffer.as_mut_ptr()),
+            Some(buffer) => (mem::size_of_val(buffer), buffer.as_mut_ptr() as *mut Handle),
             None => (0, NULL_BUFFER),
         };
 
         // Obtain the needed data from the parameters.
         let (search_type, guid, key) = match search_type {
-            LocateSearchType::ByProtocol(guid) => (2, guid as *const _, ptr::null_mut()),
-            _ => (0, ptr::null(), ptr::null_mut()),
+            LocateSearchType::ByProtocol(guid) => (2, guid as *const _, ptr::null()),
+            _ => (0, ptr::null(), ptr::null()),
         };
 
-        let status = (self.locate_handle)(search_type, &*guid, key, &mut buffer_size, buffer);
+        let status = (self.locate_handle)(search_type, guid, key, &mut buffer_size, buffer);
 
         // Must convert the returned size (in bytes) to length (number of elements).
         let buffer_len = buffer_size / handle_size;
 
         match (buffer, status) {
             (NULL_BUFFER, Status::BufferTooSmall) => Ok(buffer_len.into()),
-            (_, other_status) => other_status.into_with(|| buffer_len),
+            (_, other_status) => other_status.into_with_err(|| buffer_len, |_| buffer_len),
         }
     }
 
     /* --- Other services --- */
+    /// Returns a monotonically increasing count for the platform
+    pub unsafe fn get_next_monotonic_count(&self) -> Result<u64> {
+        let mut count = 0;
+        (self.get_next_monotonic_count)(&mut count).into_with(|| count)
+    }
+
     pub unsafe fn stall(&self, microseconds: usize) -> Result<()> {
         (self.stall)(microseconds).into()
     }
+
+    /// Copies `source` into `destination`.
+    ///
+    /// Panics if the slices have different lengths.
+    pub fn copy_mem<T: Copy>(&self, destination: &mut [T], source: &[T]) {
+        assert_eq!(destination.len(), source.len(), "copy_mem: slices have different lengths");
+        unsafe { self.copy_mem_raw(destination.as_mut_ptr() as *mut u8, source.as_ptr() as *const u8, mem::size_of_val(source)) }
+    }
+
+    /// Copies `length` bytes from `source` to `destination`, the regions may overlap.
+    pub unsafe fn copy_mem_raw(&self, destination: *mut u8, source: *const u8, length: usize) {
+        (self.copy_mem)(destination as *mut Void, source as *const Void, length)
+    }
+
+    /// Fills `buffer` with `value`.
+    pub fn set_mem(&self, buffer: &mut [u8], value: u8) {
+        unsafe { self.set_mem_raw(buffer.as_mut_ptr(), buffer.len(), value) }
+    }
+
+    /// Fills `size` bytes at `buffer` with `value`.
+    pub unsafe fn set_mem_raw(&self, buffer: *mut u8, size: usize, value: u8) {
+        (self.set_mem)(buffer as *mut Void, size, value)
+    }
 }
 
 #[repr(u32)]
@@ -190,7 +308,7 @@ pub enum ProtocolAttribute {
     /// Since OpenProtocol() performs the same function as HandleProtocol() with additional functionality, 
     /// HandleProtocol() can simply call OpenProtocol() with this Attributes value. 
     ByHandleProtocol = 0x00000001,
-    ///	Specifies the protocol to search by. 
+    /// Specifies the protocol to search by. 
     /// This parameter is only valid if SearchType is ByProtocol. 
     GetProtocol = 0x00000002,
     /// Used by a driver to test for the existence of a protocol interface on a handle. 
@@ -212,6 +330,20 @@ pub enum ProtocolAttribute {
     Exclusive = 0x00000020
 }
 
+bitflags! {
+    /// The type of an event.
+    pub struct EventType: u32 {
+        const TIMER = 0x80000000;
+        const RUNTIME = 0x40000000;
+        const NOTIFY_WAIT = 0x00000100;
+        const NOTIFY_SIGNAL = 0x00000200;
+        /// Signaled when `exit_boot_services` is called.
+        const SIGNAL_EXIT_BOOT_SERVICES = 0x00000201;
+        /// Signaled when `set_virtual_address_map` is called, runtime drivers convert their pointers here.
+        const SIGNAL_VIRTUAL_ADDRESS_CHANGE = 0x60000202;
+    }
+}
+
 #[repr(C)]
 pub enum TimerDelay {
 	Cancel,
@@ -8,10 +8,12 @@
 pub mod system;
 pub mod boot;
 pub mod runtime;
+pub mod monotonic;
 
 pub use self::system::SystemTable;
 pub use self::boot::BootServices;
 pub use self::runtime::RuntimeServices;
+pub use self::monotonic::MonotonicCounter;
 
 #[repr(C)]
 pub struct TableHeader {
@@ -0,0 +1,57 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also http://wiki.phoenix.com/wiki/index.php/GetNextMonotonicCount
+
+//* Use from local library *//
+use status::{Result, ResultExt};
+use super::{BootServices, RuntimeServices};
+
+/// The platform's 64-bit monotonic counter, usable before and after `exit_boot_services`.
+///
+/// While boot services are available the full counter is returned. Afterwards only the
+/// high 32 bits can be incremented, so values are `high << 32`, which are still larger
+/// than any value returned before and persist across reboots.
+pub struct MonotonicCounter {
+    boot_services: Option<&'static BootServices>,
+    runtime_services: &'static RuntimeServices
+}
+
+impl MonotonicCounter {
+    pub fn new(boot_services: &'static BootServices, runtime_services: &'static RuntimeServices) -> Self {
+        MonotonicCounter {
+            boot_services: Some(boot_services),
+            runtime_services
+        }
+    }
+
+    /// Creates a counter that only uses runtime services, e.g. after `exit_boot_services`.
+    pub fn runtime(runtime_services: &'static RuntimeServices) -> Self {
+        MonotonicCounter {
+            boot_services: None,
+            runtime_services
+        }
+    }
+
+    /// Stops using boot services, must be called when exiting boot services.
+    pub fn exit_boot_services(&mut self) {
+        self.boot_services = None;
+    }
+
+    /// Replaces the runtime services, e.g. with the relocated table after `set_virtual_address_map`.
+    pub fn set_runtime_services(&mut self, runtime_services: &'static RuntimeServices) {
+        self.runtime_services = runtime_services;
+    }
+
+    /// Returns the next value of the counter.
+    pub unsafe fn next(&self) -> Result<u64> {
+        match self.boot_services {
+            Some(boot_services) => boot_services.get_next_monotonic_count(),
+            None => self.runtime_services.get_next_high_monotonic_count().map_inner(|high| (high as u64) << 32)
+        }
+    }
+}
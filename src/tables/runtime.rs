@@ -7,12 +7,27 @@
 
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_RUNTIME_SERVICES
 
+//* Use from external library *//
+use core::{mem, ptr};
+use core::sync::atomic::{AtomicBool, Ordering};
+
 //* Use from local library *//
 use super::TableHeader;
-use status::{Result, Status};
-use utility::{Void, Guid, PoolPointer, MemoryDescriptor};
+use status::{Result, Status, Error};
+use utility::{Void, Guid, PhysAddress, MemoryAttribute, MemoryDescriptor, MemoryMap, CStr16};
 use utility::time::{Time, TimeCapablities};
 
+//* Constants & Types *//
+/// EFI_MEMORY_DESCRIPTOR_VERSION
+pub const MEMORY_DESCRIPTOR_VERSION: u32 = 1;
+/// EFI_OPTIONAL_PTR, a null pointer is left unchanged by `convert_pointer`.
+const OPTIONAL_POINTER: usize = 0x00000001;
+const MAX_RUNTIME_POINTERS: usize = 32;
+/// The capsule and variable info services only exist since UEFI 2.0.
+const UEFI_2_0_REVISION: u32 = 2 << 16;
+
+static VIRTUAL_MODE: AtomicBool = AtomicBool::new(false);
+
 #[repr(C)]
 pub struct RuntimeServices {
     pub hdr: TableHeader,
@@ -24,28 +39,388 @@ pub struct RuntimeServices {
     pub set_wakeup_time: efi_fcn! { fn(enable: bool, time: Option<&mut Time>) -> Status },
 
     /* --- Virutal Memory Services --- */
-    pub set_virtual_address_map: efi_fcn! { fn(memory_map_size: usize, descriptor_size: usize, descriptor_version: u32, virtual_map: &MemoryDescriptor) -> Status },
+    /// Changes the runtime addressing mode of EFI firmware from physical to virtual
+    pub set_virtual_address_map: efi_fcn! { fn(memory_map_size: usize, descriptor_size: usize, descriptor_version: u32, virtual_map: *const MemoryDescriptor) -> Status },
+    /// Determines the new virtual address that is to be used on subsequent memory accesses
     pub convert_pointer: efi_fcn! { fn(debug_disposition: usize, address: &mut *const Void) -> Status },
     
     /* --- Variable Services --- */
-    pub get_variable: efi_fcn! { fn(variable_name: &char, vender_guid: &Guid, attributes: Option<&mut u32>, data_size: &mut usize, data: &Void) -> Status },
-    pub get_next_variable_name: efi_fcn! { fn(variable_name_size: &mut usize, variable_name: &mut char, vendor_guid: &Guid) -> Status },
-    pub set_variable: efi_fcn! { fn(variable_name: &char, vendor_guid: &Guid, attributes: u32, data_size: usize, data: &Void) -> Status },
+    /// Returns the value of a variable
+    pub get_variable: efi_fcn! { fn(variable_name: *const u16, vendor_guid: &Guid, attributes: Option<&mut u32>, data_size: &mut usize, data: *mut u8) -> Status },
+    /// Enumerates the current variable names
+    pub get_next_variable_name: efi_fcn! { fn(variable_name_size: &mut usize, variable_name: *mut u16, vendor_guid: &mut Guid) -> Status },
+    /// Sets the value of a variable
+    pub set_variable: efi_fcn! { fn(variable_name: *const u16, vendor_guid: &Guid, attributes: u32, data_size: usize, data: *const u8) -> Status },
+    /// Returns the next high 32 bits of the platform's monotonic counter
     pub get_next_high_monotonic_count: efi_fcn! { fn(high_count: &mut u32) -> Status },
 
     /* --- Other Services --- */
-    pub reset_system: efi_fcn! { fn(reset_type: u32, reset_status: Status, data_size: usize, reset_data: PoolPointer<Void>) -> Status }
+    /// Resets the entire platform
+    pub reset_system: efi_fcn! { fn(reset_type: u32, reset_status: Status, data_size: usize, reset_data: *const u8) -> ! },
+
+    /* --- UEFI 2.0 Capsule Services --- */
+    /// Passes capsules to the firmware with both virtual and physical mapping
+    pub update_capsule: efi_fcn! { fn(capsule_header_array: *const *const CapsuleHeader, capsule_count: usize, scatter_gather_list: u64) -> Status },
+    /// Returns if the capsule can be supported via `update_capsule`
+    pub query_capsule_capabilities: efi_fcn! { fn(capsule_header_array: *const *const CapsuleHeader, capsule_count: usize, maximum_capsule_size: &mut u64, reset_type: &mut u32) -> Status },
+
+    /* --- UEFI 2.0 Miscellaneous Services --- */
+    /// Returns information about the EFI variable store
+    pub query_variable_info: efi_fcn! { fn(attributes: u32, maximum_variable_storage_size: &mut u64, remaining_variable_storage_size: &mut u64, maximum_variable_size: &mut u64) -> Status }
 }
 
 impl RuntimeServices {
-    pub unsafe fn reset_system(&self, reset_type: ResetType, reset_status: Status) -> Result<()> {
-        (self.reset_system)(reset_type as u32, reset_status, 0, Void::new()).into()
+    /* --- Other Services --- */
+    /// Increments the high 32 bits of the platform's monotonic counter and returns them.
+    pub unsafe fn get_next_high_monotonic_count(&self) -> Result<u32> {
+        let mut high_count = 0;
+        (self.get_next_high_monotonic_count)(&mut high_count).into_with(|| high_count)
+    }
+
+    /* --- Virutal Memory Services --- */
+    /// Switches the runtime services to virtual addressing.
+    ///
+    /// Must be called after `exit_boot_services` with the map returned by `get_memory_map`.
+    /// The caller must assign a virtual address to every region with `MemoryAttribute::RUNTIME`,
+    /// the firmware ignores the other regions. Only the region holding the runtime services is checked,
+    /// `Status::InvalidParameter` is returned if it is missing or not a runtime region.
+    /// Once the firmware succeeded, later calls return `Status::Unsupported`.
+    /// Returns the runtime services at their virtual address.
+    pub unsafe fn set_virtual_address_map(&self, map: &MemoryMap) -> Result<VirtualRuntimeServices> {
+        let table = PhysAddress::from_ptr(self);
+        let region = map.iter().find(|descriptor| descriptor.contains(table)).ok_or(Status::InvalidParameter)?;
+        if !region.attributes().contains(MemoryAttribute::RUNTIME) {
+            return Err(Status::InvalidParameter);
+        }
+        let virtual_table = region.virtual_start().get() + (table.get() - region.physical_start().get());
+
+        if VIRTUAL_MODE.load(Ordering::SeqCst) {
+            return Err(Status::Unsupported);
+        }
+
+        let bytes = map.as_bytes();
+        let status = (self.set_virtual_address_map)(bytes.len(), map.descriptor_size(), map.descriptor_version(), bytes.as_ptr() as *const MemoryDescriptor);
+        if !status.is_error() {
+            VIRTUAL_MODE.store(true, Ordering::SeqCst);
+        }
+        status.into_with(|| VirtualRuntimeServices(virtual_table as *const RuntimeServices))
+    }
+
+    /// Converts a physical pointer of a runtime driver to its virtual address.
+    ///
+    /// Only valid while handling an `EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE` event.
+    pub unsafe fn convert_pointer<T>(&self, pointer: &mut *const T) -> Result<()> {
+        let mut address = *pointer as *const Void;
+        let status = (self.convert_pointer)(OPTIONAL_POINTER, &mut address);
+        if status.is_success() {
+            *pointer = address as *const T;
+        }
+        status.into()
+    }
+
+    /* --- Variable Services --- */
+    /// Returns the value of a variable.
+    ///
+    /// Returns the size of the variable in bytes and its attributes.
+    /// If `buffer` is `None`, only the size of the variable is queried.
+    /// If it is too small, the error carries the size required.
+    pub unsafe fn get_variable(&self, name: &CStr16, vendor: &Guid, buffer: Option<&mut [u8]>) -> Result<(usize, VariableAttributes), Error<usize>> {
+        const NULL_BUFFER: *mut u8 = ptr::null_mut();
+
+        let (mut data_size, data) = match buffer {
+            Some(buffer) => (buffer.len(), buffer.as_mut_ptr()),
+            None => (0, NULL_BUFFER),
+        };
+        let mut attributes = 0u32;
+
+        let status = (self.get_variable)(name.as_ptr(), vendor, Some(&mut attributes), &mut data_size, data);
+        let attributes = VariableAttributes::from_bits_truncate(attributes);
+
+        match (data, status) {
+            (NULL_BUFFER, Status::BufferTooSmall) => Ok((data_size, attributes).into()),
+            (_, other_status) => other_status.into_with_err(|| (data_size, attributes), |_| data_size),
+        }
+    }
+
+    /// Enumerates the current variable names.
+    ///
+    /// On input `name` and `vendor` hold the previous variable (an empty string starts the search),
+    /// on output they hold the next one. Returns `Status::NotFound` once all variables were returned.
+    /// If `name` is too small, the error carries the length required in `u16`s.
+    pub unsafe fn get_next_variable_name(&self, name: &mut [u16], vendor: &mut Guid) -> Result<(), Error<usize>> {
+        let mut name_size = mem::size_of_val(name);
+        (self.get_next_variable_name)(&mut name_size, name.as_mut_ptr(), vendor)
+            .into_with_err(|| (), |_| name_size / mem::size_of::<u16>())
+    }
+
+    /// Sets the value of a variable.
+    pub unsafe fn set_variable(&self, name: &CStr16, vendor: &Guid, attributes: VariableAttributes, data: &[u8]) -> Result<()> {
+        (self.set_variable)(name.as_ptr(), vendor, attributes.bits(), data.len(), data.as_ptr()).into()
+    }
+
+    /// Deletes a variable.
+    pub unsafe fn delete_variable(&self, name: &CStr16, vendor: &Guid) -> Result<()> {
+        (self.set_variable)(name.as_ptr(), vendor, 0, 0, ptr::null()).into()
+    }
+
+    /// Returns the storage space of variables with the given attributes.
+    pub unsafe fn query_variable_info(&self, attributes: VariableAttributes) -> Result<VariableStorageInfo> {
+        self.check_uefi_2_0()?;
+        let mut info = VariableStorageInfo::default();
+        (self.query_variable_info)(attributes.bits(),
+                                   &mut info.maximum_storage_size,
+                                   &mut info.remaining_storage_size,
+                                   &mut info.maximum_variable_size).into_with(|| info)
+    }
+
+    /* --- Capsule Services --- */
+    /// Passes capsules to the firmware.
+    ///
+    /// `scatter_gather_list` describes the physical location of the capsules and is required if
+    /// any capsule has `CapsuleFlags::PERSIST_ACROSS_RESET` set.
+    pub unsafe fn update_capsule(&self, capsules: &[&CapsuleHeader], scatter_gather_list: Option<&[CapsuleBlockDescriptor]>) -> Result<()> {
+        self.check_uefi_2_0()?;
+        let scatter_gather_list = scatter_gather_list.map(|list| list.as_ptr() as u64).unwrap_or(0);
+        (self.update_capsule)(capsules.as_ptr() as *const *const CapsuleHeader, capsules.len(), scatter_gather_list).into()
+    }
+
+    /// Returns the maximum capsule size and the reset type required to process the capsules.
+    pub unsafe fn query_capsule_capabilities(&self, capsules: &[&CapsuleHeader]) -> Result<CapsuleCapabilities> {
+        self.check_uefi_2_0()?;
+        let mut maximum_capsule_size = 0;
+        let mut reset_type = 0;
+        let (_, status) = (self.query_capsule_capabilities)(capsules.as_ptr() as *const *const CapsuleHeader, capsules.len(),
+                                                            &mut maximum_capsule_size, &mut reset_type).into_with(|| ())?.split();
+        let reset_type = ResetType::from_raw(reset_type).ok_or(Status::Unsupported)?;
+        status.into_with(|| CapsuleCapabilities { maximum_capsule_size, reset_type })
+    }
+
+    fn check_uefi_2_0(&self) -> core::result::Result<(), Status> {
+        if self.hdr.revision < UEFI_2_0_REVISION {
+            return Err(Status::Unsupported);
+        }
+        Ok(())
+    }
+
+    /* --- Other Services --- */
+    /// Resets the entire platform.
+    pub unsafe fn reset_system(&self, reset_type: ResetType, reset_status: Status) -> ! {
+        (self.reset_system)(reset_type as u32, reset_status, 0, ptr::null())
+    }
+
+    /// Resets the entire platform, passing reset data to the firmware.
+    ///
+    /// `data` is a null-terminated UCS-2 string describing the reason, optionally followed by binary data.
+    /// For `ResetType::EfiResetPlatformSpecific` the binary data starts with the `Guid` of the reset type.
+    pub unsafe fn reset_system_with_data(&self, reset_type: ResetType, reset_status: Status, data: &[u8]) -> ! {
+        (self.reset_system)(reset_type as u32, reset_status, data.len(), data.as_ptr())
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
 pub enum ResetType {
+    /// Resets all circuitry within the system.
     EfiResetCold,
+    /// Resets the processors without resetting all circuitry.
     EfiResetWarm,
-    EfiResetShutdown
-}
\ No newline at end of file
+    /// Places the system in a power state equivalent to ACPI G2/S5 or G3.
+    EfiResetShutdown,
+    /// A reset defined by the `Guid` at the start of the binary reset data.
+    EfiResetPlatformSpecific
+}
+
+impl ResetType {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ResetType::EfiResetCold),
+            1 => Some(ResetType::EfiResetWarm),
+            2 => Some(ResetType::EfiResetShutdown),
+            3 => Some(ResetType::EfiResetPlatformSpecific),
+            _ => None
+        }
+    }
+}
+
+/// Storage space of the variable store, in bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VariableStorageInfo {
+    /// Storage space available for variables with the queried attributes.
+    pub maximum_storage_size: u64,
+    /// Storage space remaining for variables with the queried attributes.
+    pub remaining_storage_size: u64,
+    /// Maximum size of a single variable with the queried attributes.
+    pub maximum_variable_size: u64
+}
+
+impl VariableStorageInfo {
+    /// Returns whether a variable of `size` bytes can still be written.
+    pub fn fits(&self, size: u64) -> bool {
+        size <= self.remaining_storage_size && size <= self.maximum_variable_size
+    }
+}
+
+// See also UEFI Specification, 8.5.3 Update Capsule
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct CapsuleHeader {
+    /// Identifies the capsule type.
+    pub capsule_guid: Guid,
+    /// Size of the header, the capsule body follows it.
+    pub header_size: u32,
+    pub flags: u32,
+    /// Size of the entire capsule, including the header.
+    pub capsule_image_size: u32
+}
+
+impl CapsuleHeader {
+    /// Interprets the start of `bytes` as a capsule, which must be entirely contained in `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Option<&CapsuleHeader> {
+        if bytes.len() < mem::size_of::<CapsuleHeader>() || bytes.as_ptr() as usize % mem::align_of::<CapsuleHeader>() != 0 {
+            return None;
+        }
+        let header = unsafe { &*(bytes.as_ptr() as *const CapsuleHeader) };
+        if (header.capsule_image_size as usize) > bytes.len() || header.header_size > header.capsule_image_size {
+            return None;
+        }
+        Some(header)
+    }
+
+    pub fn capsule_flags(&self) -> CapsuleFlags {
+        CapsuleFlags::from_bits_truncate(self.flags)
+    }
+}
+
+bitflags! {
+    /// Flags of a `CapsuleHeader`, the lower 16 bits are defined by the capsule type.
+    pub struct CapsuleFlags: u32 {
+        /// The capsule is kept in memory across a system reset.
+        const PERSIST_ACROSS_RESET = 0x00010000;
+        /// The capsule is added to the configuration table after the reset, requires `PERSIST_ACROSS_RESET`.
+        const POPULATE_SYSTEM_TABLE = 0x00020000;
+        /// The firmware resets the system itself, requires `PERSIST_ACROSS_RESET`.
+        const INITIATE_RESET = 0x00040000;
+    }
+}
+
+/// An entry of the scatter-gather list passed to `update_capsule`.
+///
+/// A descriptor with a length of zero continues the list at `address`, or terminates it if `address` is zero.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct CapsuleBlockDescriptor {
+    pub length: u64,
+    pub address: u64
+}
+
+impl CapsuleBlockDescriptor {
+    /// Describes a block of capsule data at a physical address.
+    pub fn data_block(address: PhysAddress, length: u64) -> Self {
+        CapsuleBlockDescriptor { length, address: address.get() }
+    }
+
+    /// Continues the list at another physical address.
+    pub fn continuation(address: PhysAddress) -> Self {
+        CapsuleBlockDescriptor { length: 0, address: address.get() }
+    }
+
+    /// Terminates the list.
+    pub fn end() -> Self {
+        CapsuleBlockDescriptor::default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CapsuleCapabilities {
+    /// Maximum size `update_capsule` supports for the queried capsules.
+    pub maximum_capsule_size: u64,
+    /// Reset type required to process the queried capsules.
+    pub reset_type: ResetType
+}
+
+bitflags! {
+    /// Attributes of a variable.
+    pub struct VariableAttributes: u32 {
+        /// The variable is stored in non-volatile storage.
+        const NON_VOLATILE = 0x00000001;
+        /// The variable is accessible while boot services are available.
+        const BOOTSERVICE_ACCESS = 0x00000002;
+        /// The variable is accessible after `exit_boot_services` was called.
+        const RUNTIME_ACCESS = 0x00000004;
+        /// The variable is a hardware error record.
+        const HARDWARE_ERROR_RECORD = 0x00000008;
+        /// Deprecated count-based authenticated write access.
+        const AUTHENTICATED_WRITE_ACCESS = 0x00000010;
+        /// Writes must carry an `EFI_VARIABLE_AUTHENTICATION_2` header.
+        const TIME_BASED_AUTHENTICATED_WRITE_ACCESS = 0x00000020;
+        /// The data is appended to the existing value instead of replacing it.
+        const APPEND_WRITE = 0x00000040;
+        /// Writes must carry an `EFI_VARIABLE_AUTHENTICATION_3` header.
+        const ENHANCED_AUTHENTICATED_ACCESS = 0x00000080;
+    }
+}
+
+/// Runtime services relocated by `set_virtual_address_map`.
+#[derive(Clone, Copy, Debug)]
+pub struct VirtualRuntimeServices(*const RuntimeServices);
+
+impl VirtualRuntimeServices {
+    pub fn as_ptr(&self) -> *const RuntimeServices {
+        self.0
+    }
+
+    /// The virtual mapping passed to `set_virtual_address_map` must be active.
+    pub unsafe fn get(&self) -> &'static RuntimeServices {
+        &*self.0
+    }
+}
+
+/// Pointers of a runtime driver that have to follow the switch to virtual addressing.
+///
+/// Register the address of each pointer variable, then call `convert` from the
+/// `EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE` notify function.
+pub struct RuntimePointers {
+    pointers: [*mut *const Void; MAX_RUNTIME_POINTERS],
+    len: usize
+}
+
+impl Default for RuntimePointers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuntimePointers {
+    pub const fn new() -> Self {
+        RuntimePointers {
+            pointers: [ptr::null_mut(); MAX_RUNTIME_POINTERS],
+            len: 0
+        }
+    }
+
+    /// Registers a pointer variable, returns `Status::OutOfResources` if the registry is full.
+    pub fn register<T>(&mut self, pointer: *mut *const T) -> Result<()> {
+        if self.len == MAX_RUNTIME_POINTERS {
+            return Err(Status::OutOfResources);
+        }
+        self.pointers[self.len] = pointer as *mut *const Void;
+        self.len += 1;
+        Ok(().into())
+    }
+
+    /// Converts every registered pointer.
+    ///
+    /// Returns at the first error, leaving the remaining pointers unconverted.
+    /// Warnings do not stop the conversion, the last one is returned.
+    pub unsafe fn convert(&mut self, runtime_services: &RuntimeServices) -> Result<()> {
+        let mut status = Status::Success;
+        for &pointer in &self.pointers[.. self.len] {
+            let (_, pointer_status) = runtime_services.convert_pointer(&mut *pointer)?.split();
+            if !pointer_status.is_success() {
+                status = pointer_status;
+            }
+        }
+        status.into()
+    }
+}
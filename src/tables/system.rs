@@ -8,7 +8,8 @@
 //* Use from local library *//
 use protocols::console::input::TextInput;
 use protocols::console::output::TextOutput;
-use utility::{Void, Handle};
+use utility::{Void, Handle, CStr16};
+use utility::string::FromSliceWithNulError;
 use super::{TableHeader, BootServices, RuntimeServices};
 
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_SYSTEM_TABLE
@@ -34,39 +35,44 @@ pub struct SystemTable {
 }
 
 impl SystemTable {
+    /// Returns the name of the firmware vendor, or an error if it is not valid UCS-2.
+    pub fn firmware_vendor(&self) -> Result<&CStr16, FromSliceWithNulError> {
+        unsafe { CStr16::from_ptr(self.firmware_vendor) }
+    }
+
     pub fn console_in_handle(&self) -> Handle {
-        (self.console_in_handle)
+        self.console_in_handle
     }
 
     pub fn console_in(&self) -> &'static TextInput {
-        (self.console_in)
+        self.console_in
     }
 
     pub fn console_out_handle(&self) -> Handle {
-        (self.console_out_handle)
+        self.console_out_handle
     }
     
     pub fn console_err_handle(&self) -> Handle {
-        (self.console_err_handle)
+        self.console_err_handle
     }
 
     pub fn console_err(&self) -> &'static TextOutput {
-        (self.console_err)
+        self.console_err
     }
 
     pub fn runtime_services(&self) -> &'static RuntimeServices {
-        (self.runtime_services)
+        self.runtime_services
     }
 
     pub fn boot_services(&self) -> &'static BootServices {
-        (self.boot_services)
+        self.boot_services
     }
 
     pub fn entries(&self) -> usize {
-        (self.entries)
+        self.entries
     }
 
     pub fn configuration_table(&self) -> &'static Void {
-        (self.configuration_table)
+        self.configuration_table
     }
 }
\ No newline at end of file
@@ -1,6 +1,11 @@
 //! Some code was borrowed from [redox-os](https://github.com/redox-os/uefi)
 
+//* Use from external library *//
 use core::fmt;
+use core::str::FromStr;
+
+//* Use from local library *//
+use status::Status;
 
 pub const NULL_GUID                         : Guid = Guid(0x00000000, 0x0000, 0x0000, [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
 pub const MPS_TABLE_GUID                    : Guid = Guid(0xeb9d2d2f, 0x2d88, 0x11d3, [0x9a, 0x16, 0x00, 0x90, 0x27, 0x3f, 0xc1, 0x4d]);
@@ -16,6 +21,7 @@ pub const GLOBAL_VARIABLE_GUID              : Guid = Guid(0x8be4df61, 0x93ca, 0x
 pub const UV_SYSTEM_TABLE_GUID              : Guid = Guid(0x3b13a7d4, 0x633e, 0x11dd, [0x93, 0xec, 0xda, 0x25, 0x56, 0xd8, 0x95, 0x93]);
 pub const LINUX_EFI_CRASH_GUID              : Guid = Guid(0xcfc8fc79, 0xbe2e, 0x4ddc, [0x97, 0xf0, 0x9f, 0x98, 0xbf, 0xe2, 0x98, 0xa0]);
 pub const LOADED_IMAGE_PROTOCOL_GUID        : Guid = Guid(0x5b1b31a1, 0x9562, 0x11d2, [0x8e, 0x3f, 0x00, 0xa0, 0xc9, 0x69, 0x72, 0x3b]);
+pub const LOADED_IMAGE_DEVICE_PATH_PROTOCOL_GUID: Guid = Guid(0xbc62157e, 0x3e33, 0x4fec, [0x99, 0x20, 0x2d, 0x3b, 0x36, 0xd7, 0x50, 0xdf]);
 pub const GRAPHICS_OUTPUT_PROTOCOL_GUID     : Guid = Guid(0x9042a9de, 0x23dc, 0x4a38, [0x96, 0xfb, 0x7a, 0xde, 0xd0, 0x80, 0x51, 0x6a]);
 pub const UGA_PROTOCOL_GUID                 : Guid = Guid(0x982c298b, 0xf4fa, 0x41cb, [0xb8, 0x38, 0x77, 0xaa, 0x68, 0x8f, 0xb8, 0x39]);
 pub const PCI_IO_PROTOCOL_GUID              : Guid = Guid(0x4cf5b200, 0x68b8, 0x4ca5, [0x9e, 0xec, 0xb2, 0x3e, 0x3f, 0x50, 0x02, 0x9a]);
@@ -40,116 +46,248 @@ pub const DEBUG_IMAGE_INFO_TABLE_GUID       : Guid = Guid(0x49152e77, 0x1ada, 0x
 pub const SHELL_GUID                        : Guid = Guid(0x6302d008, 0x7f9b, 0x4f30, [0x87, 0xac, 0x60, 0xc9, 0xfe, 0xf5, 0xda, 0x4e]);
 pub const SHELL_PARAMETERS_GUID             : Guid = Guid(0x752f3136, 0x4e16, 0x4fdc, [0xa2, 0x2a, 0xe5, 0xf4, 0x68, 0x12, 0xf4, 0xca]);
 pub const SIMPLE_POINTER_GUID               : Guid = Guid(0x31878c87, 0x0b75, 0x11d5, [0x9a, 0x4f, 0x00, 0x90, 0x27, 0x3f, 0xc1, 0x4d]);
+pub const IMAGE_SECURITY_DATABASE_GUID      : Guid = Guid(0xd719b2cb, 0x3d3a, 0x4596, [0xa3, 0xbc, 0xda, 0xd0, 0x0e, 0x67, 0x65, 0x6f]);
+pub const CERT_SHA256_GUID                  : Guid = Guid(0xc1c41626, 0x504c, 0x4092, [0xac, 0xa9, 0x41, 0xf9, 0x36, 0x93, 0x43, 0x28]);
+pub const CERT_X509_GUID                    : Guid = Guid(0xa5c059a1, 0x94e4, 0x4aa7, [0x87, 0xb5, 0xab, 0x15, 0x5c, 0x2b, 0xf0, 0x72]);
+pub const CERT_TYPE_PKCS7_GUID              : Guid = Guid(0x4aafd29d, 0x68df, 0x49ee, [0x8a, 0xa9, 0x34, 0x7d, 0x37, 0x56, 0x65, 0xa7]);
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 #[repr(C)]
 pub struct Guid(pub u32, pub u16, pub u16, pub [u8; 8]);
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum GuidKind {
-    Null,
-    Mps,
-    Acpi,
-    Acpi2,
-    Smbios,
-    Smbios3,
-    SalSystem,
-    Hcdp,
-    UgaIo,
-    GlobalVariable,
-    SimpleTextOutput,
-    UvSystem,
-    LinuxEfiCrash,
-    LoadedImage,
-    GraphicsOutput,
-    Uga,
-    PciIo,
-    FileInfo,
-    SystemResource,
-    BlockIo,
-    FileSystem,
-    LoadFile,
-    DevicePath,
-    DeviceTree,
-    Properties,
-    Rng,
-    RngAlrorithm,
-    MemoryAttributes,
-    ConsoleOut,
-    SectionTianoCompress,
-    SectionLzmaCompress,
-    DxeServices,
-    HobList,
-    MemoryTypeInformation,
-    DebugImageInfo,
-    Shell,
-    ShellParameters,
-    SimplePointer,
-    Unknown
+macro_rules! guid_kinds {
+    ($($kind:ident => $guid:ident,)*) => {
+        /// The well-known GUIDs defined above.
+        #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+        pub enum GuidKind {
+            $($kind,)*
+            Unknown
+        }
+
+        impl GuidKind {
+            /// Returns the GUID of this kind, `None` for `Unknown`.
+            pub fn guid(&self) -> Option<Guid> {
+                match *self {
+                    $(GuidKind::$kind => Some($guid),)*
+                    GuidKind::Unknown => None
+                }
+            }
+
+            /// Returns the name of the constant defining this kind, e.g. `GLOBAL_VARIABLE_GUID`.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $(GuidKind::$kind => stringify!($guid),)*
+                    GuidKind::Unknown => "Unknown"
+                }
+            }
+        }
+
+        impl<'a> From<&'a Guid> for GuidKind {
+            fn from(guid: &'a Guid) -> Self {
+                match *guid {
+                    $($guid => GuidKind::$kind,)*
+                    _ => GuidKind::Unknown
+                }
+            }
+        }
+    };
+}
+
+guid_kinds! {
+    Null => NULL_GUID,
+    Mps => MPS_TABLE_GUID,
+    Acpi => ACPI_TABLE_GUID,
+    Acpi2 => ACPI_20_TABLE_GUID,
+    Smbios => SMBIOS_TABLE_GUID,
+    Smbios3 => SMBIOS3_TABLE_GUID,
+    SalSystem => SAL_SYSTEM_TABLE_GUID,
+    Hcdp => HCDP_TABLE_GUID,
+    UgaIo => UGA_IO_PROTOCOL_GUID,
+    GlobalVariable => GLOBAL_VARIABLE_GUID,
+    SimpleTextOutput => SIMPLE_TEXT_OUTPUT_GUID,
+    UvSystem => UV_SYSTEM_TABLE_GUID,
+    LinuxEfiCrash => LINUX_EFI_CRASH_GUID,
+    LoadedImage => LOADED_IMAGE_PROTOCOL_GUID,
+    LoadedImageDevicePath => LOADED_IMAGE_DEVICE_PATH_PROTOCOL_GUID,
+    GraphicsOutput => GRAPHICS_OUTPUT_PROTOCOL_GUID,
+    Uga => UGA_PROTOCOL_GUID,
+    PciIo => PCI_IO_PROTOCOL_GUID,
+    FileInfo => FILE_INFO_ID,
+    SystemResource => SYSTEM_RESOURCE_TABLE_GUID,
+    BlockIo => BLOCK_IO_GUID,
+    FileSystem => FILE_SYSTEM_GUID,
+    LoadFile => LOAD_FILE_GUID,
+    DevicePath => DEVICE_PATH_GUID,
+    DeviceTree => DEVICE_TREE_GUID,
+    Properties => PROPERTIES_TABLE_GUID,
+    Rng => RNG_PROTOCOL_GUID,
+    RngAlrorithm => RNG_ALGORITHM_RAW,
+    MemoryAttributes => MEMORY_ATTRIBUTES_TABLE_GUID,
+    ConsoleOut => CONSOLE_OUT_DEVICE_GUID,
+    SectionTianoCompress => SECTION_TIANO_COMPRESS_GUID,
+    SectionLzmaCompress => SECTION_LZMA_COMPRESS_GUID,
+    DxeServices => DXE_SERVICES_TABLE_GUID,
+    HobList => HOB_LIST_GUID,
+    MemoryTypeInformation => MEMORY_TYPE_INFORMATION_GUID,
+    DebugImageInfo => DEBUG_IMAGE_INFO_TABLE_GUID,
+    Shell => SHELL_GUID,
+    ShellParameters => SHELL_PARAMETERS_GUID,
+    SimplePointer => SIMPLE_POINTER_GUID,
+    ImageSecurityDatabase => IMAGE_SECURITY_DATABASE_GUID,
+    CertSha256 => CERT_SHA256_GUID,
+    CertX509 => CERT_X509_GUID,
+    CertPkcs7 => CERT_TYPE_PKCS7_GUID,
 }
 
 impl Guid {
-    pub fn kind(&self) -> GuidKind {
-        match *self {
-            NULL_GUID => GuidKind::Null,
-            MPS_TABLE_GUID => GuidKind::Mps,
-            ACPI_TABLE_GUID => GuidKind::Acpi,
-            ACPI_20_TABLE_GUID => GuidKind::Acpi2,
-            SMBIOS_TABLE_GUID => GuidKind::Smbios,
-            SMBIOS3_TABLE_GUID => GuidKind::Smbios3,
-            SAL_SYSTEM_TABLE_GUID => GuidKind::SalSystem,
-            HCDP_TABLE_GUID => GuidKind::Hcdp,
-            UGA_IO_PROTOCOL_GUID => GuidKind::UgaIo,
-            GLOBAL_VARIABLE_GUID => GuidKind::GlobalVariable,
-            SIMPLE_TEXT_OUTPUT_GUID => GuidKind::SimpleTextOutput,
-            UV_SYSTEM_TABLE_GUID => GuidKind::UvSystem,
-            LINUX_EFI_CRASH_GUID => GuidKind::LinuxEfiCrash,
-            LOADED_IMAGE_PROTOCOL_GUID => GuidKind::LoadedImage,
-            GRAPHICS_OUTPUT_PROTOCOL_GUID => GuidKind::GraphicsOutput,
-            UGA_PROTOCOL_GUID => GuidKind::Uga,
-            PCI_IO_PROTOCOL_GUID => GuidKind::PciIo,
-            FILE_INFO_ID => GuidKind::FileInfo,
-            SYSTEM_RESOURCE_TABLE_GUID => GuidKind::SystemResource,
-            BLOCK_IO_GUID => GuidKind::BlockIo,
-            FILE_SYSTEM_GUID => GuidKind::FileSystem,
-            LOAD_FILE_GUID => GuidKind::LoadFile,
-            DEVICE_PATH_GUID => GuidKind::DevicePath,
-            DEVICE_TREE_GUID => GuidKind::DeviceTree,
-            PROPERTIES_TABLE_GUID => GuidKind::Properties,
-            RNG_PROTOCOL_GUID => GuidKind::Rng,
-            RNG_ALGORITHM_RAW => GuidKind::RngAlrorithm,
-            MEMORY_ATTRIBUTES_TABLE_GUID => GuidKind::MemoryAttributes,
-            CONSOLE_OUT_DEVICE_GUID => GuidKind::ConsoleOut,
-            SECTION_TIANO_COMPRESS_GUID => GuidKind::SectionTianoCompress,
-            SECTION_LZMA_COMPRESS_GUID => GuidKind::SectionLzmaCompress,
-            DXE_SERVICES_TABLE_GUID => GuidKind::DxeServices,
-            HOB_LIST_GUID => GuidKind::HobList,
-            MEMORY_TYPE_INFORMATION_GUID => GuidKind::MemoryTypeInformation,
-            DEBUG_IMAGE_INFO_TABLE_GUID => GuidKind::DebugImageInfo,
-            SHELL_GUID => GuidKind::Shell,
-            SHELL_PARAMETERS_GUID => GuidKind::ShellParameters,
-            SIMPLE_POINTER_GUID => GuidKind::SimplePointer,
-            _ => GuidKind::Unknown
+    /// Parses the canonical `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` form, in either case.
+    pub const fn parse(string: &str) -> Option<Guid> {
+        let string = string.as_bytes();
+        if string.len() != 36 || string[8] != b'-' || string[13] != b'-' || string[18] != b'-' || string[23] != b'-' {
+            return None;
         }
+
+        // The bytes in textual order, i.e. with every field big-endian.
+        // Together with the hyphens, the digits cover every position of the string.
+        let mut bytes = [0u8; 16];
+        let mut i = 0;
+        while i < bytes.len() {
+            let offset = GUID_BYTE_OFFSETS[i];
+            let high = match hex_digit(string[offset]) {
+                Some(digit) => digit,
+                None => return None
+            };
+            let low = match hex_digit(string[offset + 1]) {
+                Some(digit) => digit,
+                None => return None
+            };
+            bytes[i] = high << 4 | low;
+            i += 1;
+        }
+
+        Some(Guid(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                  u16::from_be_bytes([bytes[4], bytes[5]]),
+                  u16::from_be_bytes([bytes[6], bytes[7]]),
+                  [bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15]]))
+    }
+
+    /// Reads a GUID from its 16-byte wire format, where the first three fields are little-endian.
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        let mut node = [0; 8];
+        node.copy_from_slice(&bytes[8 ..]);
+        Guid(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+             u16::from_le_bytes([bytes[4], bytes[5]]),
+             u16::from_le_bytes([bytes[6], bytes[7]]),
+             node)
+    }
+
+    /// Returns the 16-byte wire format, where the first three fields are little-endian.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0 .. 4].copy_from_slice(&self.0.to_le_bytes());
+        bytes[4 .. 6].copy_from_slice(&self.1.to_le_bytes());
+        bytes[6 .. 8].copy_from_slice(&self.2.to_le_bytes());
+        bytes[8 ..].copy_from_slice(&self.3);
+        bytes
+    }
+
+    pub fn kind(&self) -> GuidKind {
+        GuidKind::from(self)
     }
 }
 
 impl fmt::Display for Guid {
+    /// Formats the GUID in its canonical form, e.g. `8be4df61-93ca-11d2-aa0d-00e098032b8c`.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({:>08x}, {:>04x}, {:>04x}, [", self.0, self.1, self.2)?;
-        for (i, b) in self.3.iter().enumerate() {
-            if i > 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{:>02x}", b)?;
-        }
-        write!(f, "])")?;
-        Ok(())
+        let node = &self.3;
+        write!(f, "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+               self.0, self.1, self.2, node[0], node[1], node[2], node[3], node[4], node[5], node[6], node[7])
     }
 }
 
-impl Default for Guid {
-    fn default() -> Self {
-        Guid(0, 0, 0, [0, 0, 0, 0, 0, 0, 0, 0])
+impl FromStr for Guid {
+    type Err = Status;
+
+    /// Parses the canonical form, optionally enclosed in braces.
+    fn from_str(string: &str) -> core::result::Result<Self, Status> {
+        let string = if string.starts_with('{') && string.ends_with('}') && string.len() >= 2 {
+            &string[1 .. string.len() - 1]
+        } else {
+            string
+        };
+        Guid::parse(string).ok_or(Status::InvalidParameter)
+    }
+}
+
+impl From<[u8; 16]> for Guid {
+    fn from(bytes: [u8; 16]) -> Self {
+        Guid::from_bytes(bytes)
+    }
+}
+
+impl From<Guid> for [u8; 16] {
+    fn from(guid: Guid) -> Self {
+        guid.to_bytes()
+    }
+}
+
+/// Offsets of the two hexadecimal digits of each byte in the canonical form.
+const GUID_BYTE_OFFSETS: [usize; 16] = [0, 2, 4, 6, 9, 11, 14, 16, 19, 21, 24, 26, 28, 30, 32, 34];
+
+const fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0' ..= b'9' => Some(c - b'0'),
+        b'a' ..= b'f' => Some(c - b'a' + 10),
+        b'A' ..= b'F' => Some(c - b'A' + 10),
+        _ => None
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    const CANONICAL: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+    #[test]
+    fn round_trip() {
+        let guid = Guid::parse(CANONICAL).unwrap();
+        assert_eq!(guid, GLOBAL_VARIABLE_GUID);
+        assert_eq!(guid.to_string(), CANONICAL);
+        assert_eq!(Guid::from_bytes(guid.to_bytes()), guid);
+        assert_eq!(guid.to_bytes()[.. 4], [0x61, 0xdf, 0xe4, 0x8b]);
+    }
+
+    #[test]
+    fn either_case() {
+        assert_eq!(Guid::parse("8BE4DF61-93CA-11D2-AA0D-00E098032B8C"), Some(GLOBAL_VARIABLE_GUID));
+        assert_eq!(Guid::parse("8be4DF61-93ca-11D2-aA0d-00e098032B8c"), Some(GLOBAL_VARIABLE_GUID));
+        assert_eq!("{8BE4DF61-93CA-11D2-AA0D-00E098032B8C}".parse(), Ok(GLOBAL_VARIABLE_GUID));
+    }
+
+    #[test]
+    fn misplaced_hyphens() {
+        assert_eq!(Guid::parse("00000000-0000-0000-0000--00000000000"), None);
+        assert_eq!(Guid::parse("00000000-0000-0000-00000-00000000000"), None);
+        assert_eq!(Guid::parse("0000000-00000-0000-0000-000000000000"), None);
+        assert_eq!(Guid::parse("00000000-0000-0000-0000-0000000-0000"), None);
+        assert_eq!(Guid::parse("000000000000000000000000000000000000"), None);
+        assert_eq!("00000000-0000-0000-0000--00000000000".parse::<Guid>(), Err(Status::InvalidParameter));
+    }
+
+    #[test]
+    fn wrong_length() {
+        assert_eq!(Guid::parse(""), None);
+        assert_eq!(Guid::parse(&CANONICAL[.. 35]), None);
+        assert_eq!(Guid::parse("8be4df61-93ca-11d2-aa0d-00e098032b8c0"), None);
+        assert_eq!(Guid::parse("8be4df6193ca11d2aa0d00e098032b8c"), None);
+        assert_eq!("{}".parse::<Guid>(), Err(Status::InvalidParameter));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn invalid_digits() {
+        assert_eq!(Guid::parse("8be4df61-93ca-11d2-aa0d-00e098032b8g"), None);
+        assert_eq!(Guid::parse("+be4df61-93ca-11d2-aa0d-00e098032b8c"), None);
+        assert_eq!(Guid::parse("8be4df61-93ca-11d2-aa0d-00e098032b\u{e9}"), None);
+    }
+}
@@ -5,32 +5,249 @@
 //   https://opensource.org/licenses/BSD-3-Clause)
 // =======================================================================
 
+//* Use from external library *//
+use core::{fmt, mem, ptr};
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
 //* Use from local library *//
-use super::{PoolPointer, Void};
+use super::PoolPointer;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct PhysAddress(u64);
+//* Constants & Types *//
+/// Size of the pages used by `allocate_pages` and memory descriptors.
+pub const PAGE_SIZE: u64 = 4096;
+
+macro_rules! address_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        #[repr(transparent)]
+        pub struct $name(u64);
+
+        impl $name {
+            pub const fn new(address: u64) -> Self {
+                $name(address)
+            }
+
+            pub fn from_ptr<T>(ptr: *const T) -> Self {
+                $name(ptr as usize as u64)
+            }
+
+            pub fn get(&self) -> u64 {
+                self.0
+            }
+
+            pub fn as_ptr<T>(&self) -> *const T {
+                self.0 as usize as *const T
+            }
+
+            pub fn as_mut_ptr<T>(&self) -> *mut T {
+                self.0 as usize as *mut T
+            }
+
+            /// Returns whether the address is a multiple of `align`, which must be a power of two.
+            pub fn is_aligned(&self, align: u64) -> bool {
+                assert!(align.is_power_of_two(), "alignment must be a power of two");
+                self.0 & (align - 1) == 0
+            }
+
+            /// Rounds the address down to a multiple of `align`, which must be a power of two.
+            pub fn align_down(&self, align: u64) -> Self {
+                assert!(align.is_power_of_two(), "alignment must be a power of two");
+                $name(self.0 & !(align - 1))
+            }
+
+            /// Rounds the address up to a multiple of `align`, which must be a power of two.
+            ///
+            /// Panics if the result does not fit in 64 bits.
+            pub fn align_up(&self, align: u64) -> Self {
+                assert!(align.is_power_of_two(), "alignment must be a power of two");
+                let address = self.0.checked_add(align - 1).expect("address overflow");
+                $name(address & !(align - 1))
+            }
+
+            pub fn is_page_aligned(&self) -> bool {
+                self.is_aligned(PAGE_SIZE)
+            }
+
+            /// Offset of the address inside its page.
+            pub fn page_offset(&self) -> u64 {
+                self.0 % PAGE_SIZE
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, concat!(stringify!($name), "({:#x})"), self.0)
+            }
+        }
+
+        impl Add<u64> for $name {
+            type Output = $name;
+
+            fn add(self, offset: u64) -> $name {
+                $name(self.0 + offset)
+            }
+        }
+
+        impl AddAssign<u64> for $name {
+            fn add_assign(&mut self, offset: u64) {
+                self.0 += offset;
+            }
+        }
+
+        impl Sub<u64> for $name {
+            type Output = $name;
+
+            fn sub(self, offset: u64) -> $name {
+                $name(self.0 - offset)
+            }
+        }
+
+        impl SubAssign<u64> for $name {
+            fn sub_assign(&mut self, offset: u64) {
+                self.0 -= offset;
+            }
+        }
+
+        impl Sub<$name> for $name {
+            type Output = u64;
+
+            fn sub(self, other: $name) -> u64 {
+                self.0 - other.0
+            }
+        }
+    };
+}
+
+address_type!(
+    /// A physical address, always 64 bits wide regardless of the pointer width.
+    PhysAddress);
+
+address_type!(
+    /// A virtual address, always 64 bits wide regardless of the pointer width.
+    VirtAddress);
 
 impl PhysAddress {
-    pub fn new(address: u64) -> Self{
-        PhysAddress(address)
+    pub fn from_mut_u8(ptr: PoolPointer<u8>) -> Self {
+        PhysAddress::from_ptr(ptr)
     }
+}
 
-    pub fn from_mut_u8(ptr: PoolPointer<u8>) -> Self {
-        PhysAddress(ptr as u64)
+/// A physical page of `PAGE_SIZE` bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Page(u64);
+
+impl Page {
+    pub const fn from_number(number: u64) -> Self {
+        Page(number)
     }
 
-    pub fn from_ptr(ptr: PoolPointer<Void>) -> Self {
-        PhysAddress(ptr as u64)
+    /// Returns the page containing the address.
+    pub fn containing(address: PhysAddress) -> Self {
+        Page(address.get() / PAGE_SIZE)
     }
 
-    pub fn get(&self) -> u64 {
+    /// Returns the page starting at the address, `None` if the address is not page aligned.
+    pub fn from_start_address(address: PhysAddress) -> Option<Self> {
+        if address.is_page_aligned() {
+            Some(Page::containing(address))
+        } else {
+            None
+        }
+    }
+
+    pub fn number(&self) -> u64 {
         self.0
     }
+
+    pub fn start_address(&self) -> PhysAddress {
+        PhysAddress::new(self.0 * PAGE_SIZE)
+    }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct VirtAddress(u64);
+impl Add<u64> for Page {
+    type Output = Page;
+
+    fn add(self, count: u64) -> Page {
+        Page(self.0 + count)
+    }
+}
+
+impl Sub<u64> for Page {
+    type Output = Page;
+
+    fn sub(self, count: u64) -> Page {
+        Page(self.0 - count)
+    }
+}
+
+impl Sub<Page> for Page {
+    type Output = u64;
+
+    fn sub(self, other: Page) -> u64 {
+        self.0 - other.0
+    }
+}
+
+/// A range of contiguous physical pages.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct PageRange {
+    start: Page,
+    count: u64
+}
+
+impl PageRange {
+    pub fn new(start: Page, count: u64) -> Self {
+        PageRange { start, count }
+    }
+
+    /// Returns the smallest range containing the `size` bytes starting at `address`.
+    pub fn covering(address: PhysAddress, size: u64) -> Self {
+        let start = Page::containing(address);
+        let end = Page::containing((address + size).align_up(PAGE_SIZE));
+        PageRange::new(start, end - start)
+    }
+
+    pub fn start(&self) -> Page {
+        self.start
+    }
+
+    /// Returns the first page after the range.
+    pub fn end(&self) -> Page {
+        self.start + self.count
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn start_address(&self) -> PhysAddress {
+        self.start.start_address()
+    }
+
+    /// Returns the first address after the range.
+    pub fn end_address(&self) -> PhysAddress {
+        self.end().start_address()
+    }
+
+    /// Size of the range in bytes.
+    pub fn size(&self) -> u64 {
+        self.count * PAGE_SIZE
+    }
+
+    pub fn contains(&self, address: PhysAddress) -> bool {
+        let page = Page::containing(address);
+        page >= self.start && page < self.end()
+    }
+
+    pub fn pages(&self) -> impl Iterator<Item = Page> {
+        (self.start.0 .. self.end().0).map(Page)
+    }
+}
 
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_MEMORY_DESCRIPTOR
 
@@ -44,81 +261,316 @@ pub struct MemoryDescriptor {
     attribute: MemoryAttribute
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-#[repr(u64)]
-pub enum MemoryAttribute {
-    ///
-    /// Memory cacheability attribute: 
-    ///
+impl MemoryDescriptor {
+    /// Describes a region which has no virtual mapping yet.
+    pub fn new(memory_type: MemoryType, physical_start: PhysAddress, page_count: u64, attributes: MemoryAttribute) -> Self {
+        MemoryDescriptor {
+            mem_type: memory_type,
+            physical_start,
+            virtual_start: VirtAddress::new(0),
+            count: page_count,
+            attribute: attributes
+        }
+    }
 
-    /// Being configured as not cacheable. 
-    EfiMemoryUC = 0x0000000000000001,
-    /// Being configured as write combining.  
-    EfiMemoryWC = 0x0000000000000002,
-    /// Being configured as cacheable with a “write through” policy. 
-    /// Writes that hit in the cache will also be written to main memory. 
-    EfiMemoryWT = 0x0000000000000004, 
-    /// Being configured as cacheable with a “write back” policy. 
-    /// Reads and writes that hit in the cache do not propagate to main memory. 
-    /// Dirty data is written back to main memory when a new cache line is allocated. 
-    EfiMemoryWB = 0x0000000000000008, 
-    /// Being configured as not cacheable, exported, and supports the “fetch and add” semaphore mechanism. 
-    EfiMemoryUCE = 0x0000000000000010,
+    pub fn memory_type(&self) -> MemoryType {
+        self.mem_type
+    }
 
-    ///
-    /// Physical memory protection attribute:
-    /// 
-    
-    /// Being configured as write-protected by system hardware. 
-    EfiMemoryWP = 0x0000000000001000,
-    /// Being configured as read-protected by system hardware. 
-    EfiMemoryRP = 0x0000000000002000,
-    /// Being configured so it is protected by system hardware from executing code.  
-    EfiMemoryXP = 0x0000000000004000,
+    pub fn physical_start(&self) -> PhysAddress {
+        self.physical_start
+    }
+
+    pub fn virtual_start(&self) -> VirtAddress {
+        self.virtual_start
+    }
+
+    /// Assigns the virtual address of the region before calling `set_virtual_address_map`.
+    pub fn set_virtual_start(&mut self, address: VirtAddress) {
+        self.virtual_start = address;
+    }
+
+    /// Number of `PAGE_SIZE` pages in the region.
+    pub fn page_count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn pages(&self) -> PageRange {
+        PageRange::new(Page::containing(self.physical_start), self.count)
+    }
+
+    pub fn attributes(&self) -> MemoryAttribute {
+        self.attribute
+    }
+
+    /// Size of the region in bytes.
+    pub fn size(&self) -> u64 {
+        self.count * PAGE_SIZE
+    }
+
+    /// Returns the first address after the region.
+    pub fn physical_end(&self) -> PhysAddress {
+        self.physical_start + self.size()
+    }
+
+    /// Returns whether the physical address lies inside the region.
+    pub fn contains(&self, address: PhysAddress) -> bool {
+        address >= self.physical_start && address - self.physical_start < self.size()
+    }
+}
 
+/// A memory map in the layout returned by `get_memory_map`.
+///
+/// The firmware may use descriptors larger than `MemoryDescriptor`, so they are read and
+/// written at a stride of `descriptor_size`, the extra bytes of each descriptor are kept as is.
+pub struct MemoryMap<'a> {
+    buffer: &'a mut [u8],
+    descriptor_size: usize,
+    descriptor_version: u32
+}
+
+impl<'a> MemoryMap<'a> {
+    /// Wraps the first `map_size` bytes of the buffer passed to `get_memory_map`.
     ///
-    /// Runtime memory attribute:
-    /// 
+    /// Returns `None` if the descriptors are smaller than `MemoryDescriptor`
+    /// or the size is not a whole number of descriptors.
+    pub fn new(buffer: &'a mut [u8], map_size: usize, descriptor_size: usize, descriptor_version: u32) -> Option<Self> {
+        if descriptor_size < mem::size_of::<MemoryDescriptor>() || map_size > buffer.len() || map_size % descriptor_size != 0 {
+            return None;
+        }
+        Some(MemoryMap {
+            buffer: &mut buffer[.. map_size],
+            descriptor_size,
+            descriptor_version
+        })
+    }
+
+    /// Number of descriptors in the map.
+    pub fn len(&self) -> usize {
+        self.buffer.len() / self.descriptor_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn descriptor_size(&self) -> usize {
+        self.descriptor_size
+    }
+
+    pub fn descriptor_version(&self) -> u32 {
+        self.descriptor_version
+    }
+
+    /// Returns the map as passed to the firmware.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buffer
+    }
+
+    /// Returns a copy of the descriptor at `index`.
+    pub fn get(&self, index: usize) -> Option<MemoryDescriptor> {
+        if index >= self.len() {
+            return None;
+        }
+        unsafe { Some(ptr::read_unaligned(self.buffer[index * self.descriptor_size ..].as_ptr() as *const MemoryDescriptor)) }
+    }
+
+    /// Replaces the descriptor at `index`, e.g. after `set_virtual_start`.
+    pub fn set(&mut self, index: usize, descriptor: MemoryDescriptor) {
+        assert!(index < self.len(), "memory descriptor index out of range");
+        unsafe { ptr::write_unaligned(self.buffer[index * self.descriptor_size ..].as_mut_ptr() as *mut MemoryDescriptor, descriptor) }
+    }
+
+    /// Returns copies of the descriptors, in map order.
+    pub fn iter(&self) -> impl Iterator<Item = MemoryDescriptor> + '_ {
+        (0 .. self.len()).filter_map(move |index| self.get(index))
+    }
+}
+
+bitflags! {
+    /// Attributes of a memory region, a region usually has several of them, e.g. `UC | WC | WT | WB`.
+    pub struct MemoryAttribute: u64 {
+        /* --- Memory cacheability attribute --- */
+        /// Being configured as not cacheable.
+        const UC = 0x0000000000000001;
+        /// Being configured as write combining.
+        const WC = 0x0000000000000002;
+        /// Being configured as cacheable with a “write through” policy.
+        /// Writes that hit in the cache will also be written to main memory.
+        const WT = 0x0000000000000004;
+        /// Being configured as cacheable with a “write back” policy.
+        /// Reads and writes that hit in the cache do not propagate to main memory.
+        /// Dirty data is written back to main memory when a new cache line is allocated.
+        const WB = 0x0000000000000008;
+        /// Being configured as not cacheable, exported, and supports the “fetch and add” semaphore mechanism.
+        const UCE = 0x0000000000000010;
+
+        /* --- Physical memory protection attribute --- */
+        /// Being configured as write-protected by system hardware.
+        const WP = 0x0000000000001000;
+        /// Being configured as read-protected by system hardware.
+        const RP = 0x0000000000002000;
+        /// Being configured so it is protected by system hardware from executing code.
+        const XP = 0x0000000000004000;
+        /// Being configured as read-only by system hardware.
+        const RO = 0x0000000000020000;
+
+        /* --- Other attributes --- */
+        /// Refers to persistent memory.
+        const NV = 0x0000000000008000;
+        /// Provides higher reliability relative to other memory in the system.
+        const MORE_RELIABLE = 0x0000000000010000;
+        /// Earmarked for specific purposes such as for specific device drivers or applications.
+        const SP = 0x0000000000040000;
+        /// Capable of being protected with the CPU's memory cryptographic capabilities.
+        const CPU_CRYPTO = 0x0000000000080000;
 
-    /// The memory region needs to be given a virtual mapping by the operating system when SetVirtualAddressMap() is called. 
-    EfiMemoryRuntime = 0x8000000000000000
+        /* --- Runtime memory attribute --- */
+        /// The memory region needs to be given a virtual mapping by the operating system when SetVirtualAddressMap() is called.
+        const RUNTIME = 0x8000000000000000;
+    }
 }
 
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_MEMORY_TYPE
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-#[repr(C)]
-pub enum MemoryType {
+/// The type of a memory region.
+///
+/// Values from 0x70000000 are reserved for OEMs and from 0x80000000 for OS loaders,
+/// unknown values are preserved as is.
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+pub struct MemoryType(u32);
+
+macro_rules! memory_types {
+    ($($(#[$attr:meta])* $name:ident = $value:expr,)*) => {
+        #[allow(non_upper_case_globals)]
+        impl MemoryType {
+            $(
+                $(#[$attr])*
+                pub const $name: MemoryType = MemoryType($value);
+            )*
+
+            /// Returns the name of a memory type defined by the specification.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(MemoryType::$name => Some(stringify!($name)),)*
+                    _ => None
+                }
+            }
+        }
+    };
+}
+
+memory_types! {
     /// Not used.
-    EfiReservedMemoryType, 
-    /// The code portions of a loaded application. 
-    /// 
+    EfiReservedMemoryType = 0,
+    /// The code portions of a loaded application.
+    ///
     /// (Note that UEFI OS loaders are UEFI applications.)
-    EfiLoaderCode, 
-    /// The data portions of a loaded application and the default data allocation type used by an application to allocate pool memory. 
-    EfiLoaderData,
-    /// The code portions of a loaded Boot Services Driver. 
-    EfiBootServicesCode,
-    /// The data portions of a loaded Boot Serves Driver, and the default data allocation type used by a Boot Services Driver to allocate pool memory. 
-    EfiBootServicesData,
-    /// The code portions of a loaded Runtime Services Driver. 
-    EfiRuntimeServicesCode,
-    /// The data portions of a loaded Runtime Services Driver and the default data allocation type used by a Runtime Services Driver to allocate pool memory. 
-    EfiRuntimeServicesData,
+    EfiLoaderCode = 1,
+    /// The data portions of a loaded application and the default data allocation type used by an application to allocate pool memory.
+    EfiLoaderData = 2,
+    /// The code portions of a loaded Boot Services Driver.
+    EfiBootServicesCode = 3,
+    /// The data portions of a loaded Boot Serves Driver, and the default data allocation type used by a Boot Services Driver to allocate pool memory.
+    EfiBootServicesData = 4,
+    /// The code portions of a loaded Runtime Services Driver.
+    EfiRuntimeServicesCode = 5,
+    /// The data portions of a loaded Runtime Services Driver and the default data allocation type used by a Runtime Services Driver to allocate pool memory.
+    EfiRuntimeServicesData = 6,
     /// Free (unallocated) memory.
-    EfiConventionalMemory,
-    /// Memory in which errors have been detected. 
-    EfiUnusableMemory, 
-    /// Memory that holds the ACPI tables. 
-    EfiACPIReclaimMemory,
+    EfiConventionalMemory = 7,
+    /// Memory in which errors have been detected.
+    EfiUnusableMemory = 8,
+    /// Memory that holds the ACPI tables.
+    EfiACPIReclaimMemory = 9,
     /// Address space reserved for use by the firmware.
-    EfiACPIMemoryNVS,
-    /// Used by system firmware to request that a memory-mapped IO region be mapped by the OS to a virtual address so it can be accessed by EFI runtime services. 
-    EfiMemoryMappedIO,
-    /// System memory-mapped IO region that is used to translate memory cycles to IO cycles by the processor. Note: There is only one region of type EfiMemoryMappedIoPortSpace defined in the architecture for Itanium-based platforms. As a result, there should be one and only one region of type EfiMemoryMappedIoPortSpace in the EFI memory map of an Itanium-based platform. 
-    EfiMemoryMappedIOPortSpace,
-    /// Address space reserved by the firmware for code that is part of the processor. 
-    EfiPalCode,
-    EfiPersistentMemory,
-    EfiMaxMemoryType
-}
\ No newline at end of file
+    EfiACPIMemoryNVS = 10,
+    /// Used by system firmware to request that a memory-mapped IO region be mapped by the OS to a virtual address so it can be accessed by EFI runtime services.
+    EfiMemoryMappedIO = 11,
+    /// System memory-mapped IO region that is used to translate memory cycles to IO cycles by the processor. Note: There is only one region of type EfiMemoryMappedIoPortSpace defined in the architecture for Itanium-based platforms. As a result, there should be one and only one region of type EfiMemoryMappedIoPortSpace in the EFI memory map of an Itanium-based platform.
+    EfiMemoryMappedIOPortSpace = 12,
+    /// Address space reserved by the firmware for code that is part of the processor.
+    EfiPalCode = 13,
+    /// A memory region that operates as EfiConventionalMemory, but happens to also support byte-addressable non-volatility.
+    EfiPersistentMemory = 14,
+    /// Memory that must be accepted by the boot target before it can be used.
+    EfiUnacceptedMemoryType = 15,
+    EfiMaxMemoryType = 16,
+}
+
+impl MemoryType {
+    /// Wraps a raw memory type, e.g. one defined by an OEM or OS loader.
+    pub const fn from_raw(value: u32) -> Self {
+        MemoryType(value)
+    }
+
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+
+    /// Returns whether the type is in the range reserved for OEMs.
+    pub fn is_oem(self) -> bool {
+        self.0 >= 0x70000000 && self.0 < 0x80000000
+    }
+
+    /// Returns whether the type is in the range reserved for OS loaders.
+    pub fn is_os(self) -> bool {
+        self.0 >= 0x80000000
+    }
+}
+
+impl fmt::Debug for MemoryType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "MemoryType({:#x})", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTOR_SIZE: usize = 48;
+
+    fn descriptor(memory_type: MemoryType, start: u64, pages: u64) -> MemoryDescriptor {
+        MemoryDescriptor::new(memory_type, PhysAddress::new(start), pages, MemoryAttribute::WB)
+    }
+
+    #[test]
+    fn memory_map_stride() {
+        let mut buffer = [0xAAu8; 4 * DESCRIPTOR_SIZE];
+        {
+            let mut map = MemoryMap::new(&mut buffer, 3 * DESCRIPTOR_SIZE, DESCRIPTOR_SIZE, 1).unwrap();
+            assert_eq!(map.len(), 3);
+            for index in 0 .. 3 {
+                map.set(index, descriptor(MemoryType::EfiConventionalMemory, index as u64 * 0x10000, 16));
+            }
+            let mut runtime = descriptor(MemoryType::EfiRuntimeServicesData, 0x20000, 1);
+            runtime.set_virtual_start(VirtAddress::new(0xFFFF_8000_0000_0000));
+            map.set(2, runtime);
+
+            assert_eq!(map.get(1), Some(descriptor(MemoryType::EfiConventionalMemory, 0x10000, 16)));
+            assert_eq!(map.get(2).map(|descriptor| descriptor.virtual_start().get()), Some(0xFFFF_8000_0000_0000));
+            assert_eq!(map.get(3), None);
+            assert_eq!(map.iter().count(), 3);
+            assert_eq!(map.as_bytes().len(), 3 * DESCRIPTOR_SIZE);
+        }
+        // The bytes after each descriptor and after the map are left alone.
+        let descriptor_end = mem::size_of::<MemoryDescriptor>();
+        assert!(buffer[descriptor_end .. DESCRIPTOR_SIZE].iter().all(|&byte| byte == 0xAA));
+        assert!(buffer[3 * DESCRIPTOR_SIZE ..].iter().all(|&byte| byte == 0xAA));
+    }
+
+    #[test]
+    fn memory_map_rejects_bad_sizes() {
+        let mut buffer = [0u8; 2 * DESCRIPTOR_SIZE];
+        assert!(MemoryMap::new(&mut buffer, 2 * DESCRIPTOR_SIZE, DESCRIPTOR_SIZE, 1).is_some());
+        assert!(MemoryMap::new(&mut buffer, DESCRIPTOR_SIZE + 8, DESCRIPTOR_SIZE, 1).is_none());
+        assert!(MemoryMap::new(&mut buffer, 3 * DESCRIPTOR_SIZE, DESCRIPTOR_SIZE, 1).is_none());
+        assert!(MemoryMap::new(&mut buffer, 32, 16, 1).is_none());
+        assert!(MemoryMap::new(&mut buffer, 0, DESCRIPTOR_SIZE, 1).map(|map| map.is_empty()).unwrap());
+    }
+}
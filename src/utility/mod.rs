@@ -7,14 +7,17 @@
 
 pub mod guid; 
 pub mod memory;
+pub mod string;
 pub mod time;
 
 //* Use from external library *//
 use core::ffi::c_void;
+use core::ptr::NonNull;
 
 //* Use from local library *//
 pub use self::guid::Guid;
-pub use self::memory::{PhysAddress, VirtAddress, MemoryType, MemoryDescriptor};
+pub use self::memory::{PAGE_SIZE, PhysAddress, VirtAddress, Page, PageRange, MemoryType, MemoryAttribute, MemoryDescriptor, MemoryMap};
+pub use self::string::{CStr8, CStr16};
 pub use self::time::Time;
 
 pub enum Void {}
@@ -33,10 +36,44 @@ pub type PoolPointer<T> = *mut T;
 
 pub type EventNotifyFcn = efi_fcn!{ fn(Event, *mut Void) -> () };
 
-#[derive(Clone, Copy)]
+/// An event created by `create_event` or handed out by the firmware.
+///
+/// Events are not `Copy`, so that `close_event` can take ownership of them.
+/// Use `Option<Event>` where the firmware may return or expect no event.
+#[derive(Debug, Eq, PartialEq, Hash)]
 #[repr(transparent)]
-pub struct Event(*mut c_void);
+pub struct Event(NonNull<c_void>);
 
-#[derive(Clone, Copy)]
+impl Event {
+    /// Wraps a raw event, returns `None` if it is null.
+    pub unsafe fn from_ptr(ptr: *mut c_void) -> Option<Self> {
+        NonNull::new(ptr).map(Event)
+    }
+
+    /// Creates another reference to the event, it must not be used once the event is closed.
+    pub unsafe fn unsafe_clone(&self) -> Self {
+        Event(self.0)
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+/// A handle to a collection of protocols, e.g. an image or a device.
+///
+/// Use `Option<Handle>` where the firmware may return or expect no handle.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 #[repr(transparent)]
-pub struct Handle(*mut c_void);
+pub struct Handle(NonNull<c_void>);
+
+impl Handle {
+    /// Wraps a raw handle, returns `None` if it is null.
+    pub unsafe fn from_ptr(ptr: *mut c_void) -> Option<Self> {
+        NonNull::new(ptr).map(Handle)
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
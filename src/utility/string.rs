@@ -0,0 +1,531 @@
+// =======================================================================
+//  Copyleft SnowFlakeOS Team 2018-∞.
+//  Distributed under the terms of the 3-Clause BSD License.
+//  (See accompanying file LICENSE or copy at
+//   https://opensource.org/licenses/BSD-3-Clause)
+// =======================================================================
+
+// See also UEFI Specification, 2.3.1 Data Types (CHAR8, CHAR16)
+
+//* Use from external library *//
+use core::{char, fmt, slice};
+
+//* Use from local library *//
+use status::Status;
+
+/// A borrowed null-terminated UCS-2 string, the `CHAR16*` strings of the firmware.
+///
+/// UCS-2 only covers the Basic Multilingual Plane, surrogates are not valid characters.
+/// A `CStr16` always ends with its only null and contains no surrogates: every constructor
+/// checks this, except `from_u16_with_nul_unchecked` whose caller must guarantee it.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct CStr16([u16]);
+
+/// A borrowed null-terminated 8-bit string, e.g. from SMBIOS or ACPI tables.
+///
+/// Bytes are read as Latin-1, so every byte is a character and the first 256 code points are representable.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct CStr8([u8]);
+
+/// Error converting a `&str` to a UCS-2 or Latin-1 string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FromStrError {
+    /// The character at this byte offset is not representable in the encoding.
+    InvalidChar(usize),
+    /// The string contains a null character at this byte offset.
+    InteriorNul(usize),
+    /// The buffer is too small to hold the string and its terminator.
+    BufferTooSmall
+}
+
+/// Error converting a `&[u16]` or `&[u8]` to a UCS-2 or Latin-1 string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FromSliceWithNulError {
+    /// The code unit at this index is a surrogate, never returned for Latin-1.
+    InvalidChar(usize),
+    /// The slice contains a null before its end, at this index.
+    InteriorNul(usize),
+    /// The slice does not end with a null.
+    NotNulTerminated
+}
+
+impl CStr16 {
+    /// Wraps a slice ending with the only null of the string.
+    pub fn from_u16_with_nul(codes: &[u16]) -> Result<&Self, FromSliceWithNulError> {
+        match codes.iter().position(|&code| code == 0) {
+            Some(nul) if nul + 1 == codes.len() => {},
+            Some(nul) => return Err(FromSliceWithNulError::InteriorNul(nul)),
+            None => return Err(FromSliceWithNulError::NotNulTerminated)
+        }
+        if let Some(index) = codes.iter().position(|&code| is_surrogate(code)) {
+            return Err(FromSliceWithNulError::InvalidChar(index));
+        }
+        Ok(unsafe { CStr16::from_u16_with_nul_unchecked(codes) })
+    }
+
+    /// Wraps the start of a slice up to and including its first null,
+    /// e.g. a buffer filled by the firmware.
+    pub fn from_u16_until_nul(codes: &[u16]) -> Result<&Self, FromSliceWithNulError> {
+        match codes.iter().position(|&code| code == 0) {
+            Some(nul) => CStr16::from_u16_with_nul(&codes[..= nul]),
+            None => Err(FromSliceWithNulError::NotNulTerminated)
+        }
+    }
+
+    /// Wraps a slice without checking it, it must end with its only null and contain no surrogates.
+    pub const unsafe fn from_u16_with_nul_unchecked(codes: &[u16]) -> &Self {
+        &*(codes as *const [u16] as *const CStr16)
+    }
+
+    /// Wraps a null-terminated string returned by the firmware, failing if it contains surrogates.
+    ///
+    /// `ptr` must point to a null-terminated string which lives for `'a`.
+    pub unsafe fn from_ptr<'a>(ptr: *const u16) -> Result<&'a Self, FromSliceWithNulError> {
+        let mut len = 0;
+        while *ptr.add(len) != 0 {
+            len += 1;
+        }
+        CStr16::from_u16_with_nul(slice::from_raw_parts(ptr, len + 1))
+    }
+
+    /// Encodes `string` into `buffer` and returns the part of it holding the string.
+    pub fn from_str_with_buf<'a>(string: &str, buffer: &'a mut [u16]) -> Result<&'a Self, FromStrError> {
+        let mut len = 0;
+        for (offset, c) in string.char_indices() {
+            let code = match c {
+                '\0' => return Err(FromStrError::InteriorNul(offset)),
+                c if (c as u32) > 0xFFFF => return Err(FromStrError::InvalidChar(offset)),
+                c => c as u16
+            };
+            *buffer.get_mut(len).ok_or(FromStrError::BufferTooSmall)? = code;
+            len += 1;
+        }
+        *buffer.get_mut(len).ok_or(FromStrError::BufferTooSmall)? = 0;
+        Ok(unsafe { CStr16::from_u16_with_nul_unchecked(&buffer[..= len]) })
+    }
+
+    pub fn as_ptr(&self) -> *const u16 {
+        self.0.as_ptr()
+    }
+
+    /// Returns the code units without the terminator.
+    pub fn as_slice(&self) -> &[u16] {
+        &self.0[.. self.len()]
+    }
+
+    pub fn as_slice_with_nul(&self) -> &[u16] {
+        &self.0
+    }
+
+    /// Returns the number of code units without the terminator.
+    pub fn len(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size in bytes including the terminator, as expected by most services.
+    pub fn size_in_bytes(&self) -> usize {
+        self.0.len() * 2
+    }
+
+    pub fn chars(&self) -> Chars<'_> {
+        Chars(self.as_slice().iter())
+    }
+}
+
+impl fmt::Display for CStr16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.chars() {
+            fmt::Write::write_char(f, c)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CStr16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Write::write_char(f, '"')?;
+        for c in self.chars().flat_map(char::escape_debug) {
+            fmt::Write::write_char(f, c)?;
+        }
+        fmt::Write::write_char(f, '"')
+    }
+}
+
+impl PartialEq<str> for CStr16 {
+    fn eq(&self, other: &str) -> bool {
+        self.chars().eq(other.chars())
+    }
+}
+
+impl<'a> PartialEq<&'a str> for CStr16 {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+impl Default for &CStr16 {
+    fn default() -> Self {
+        unsafe { CStr16::from_u16_with_nul_unchecked(&[0]) }
+    }
+}
+
+impl AsRef<CStr16> for CStr16 {
+    fn as_ref(&self) -> &CStr16 {
+        self
+    }
+}
+
+/// Iterator over the characters of a `CStr16`.
+#[derive(Clone, Debug)]
+pub struct Chars<'a>(slice::Iter<'a, u16>);
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.0.next().map(|&code| char::from_u32(code as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Chars<'a> {}
+
+impl fmt::Display for FromStrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromStrError::InvalidChar(offset) => write!(f, "character at byte {} is not representable", offset),
+            FromStrError::InteriorNul(offset) => write!(f, "null character at byte {}", offset),
+            FromStrError::BufferTooSmall => f.write_str("buffer too small")
+        }
+    }
+}
+
+impl fmt::Display for FromSliceWithNulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromSliceWithNulError::InvalidChar(index) => write!(f, "invalid character at index {}", index),
+            FromSliceWithNulError::InteriorNul(index) => write!(f, "null at index {} before the end", index),
+            FromSliceWithNulError::NotNulTerminated => f.write_str("missing null terminator")
+        }
+    }
+}
+
+impl From<FromStrError> for Status {
+    fn from(error: FromStrError) -> Self {
+        match error {
+            FromStrError::BufferTooSmall => Status::BufferTooSmall,
+            _ => Status::InvalidParameter
+        }
+    }
+}
+
+impl From<FromSliceWithNulError> for Status {
+    fn from(_: FromSliceWithNulError) -> Self {
+        Status::CompromisedData
+    }
+}
+
+impl CStr8 {
+    /// Wraps a slice ending with the only null of the string.
+    pub fn from_bytes_with_nul(bytes: &[u8]) -> Result<&Self, FromSliceWithNulError> {
+        match bytes.iter().position(|&byte| byte == 0) {
+            Some(nul) if nul + 1 == bytes.len() => Ok(unsafe { CStr8::from_bytes_with_nul_unchecked(bytes) }),
+            Some(nul) => Err(FromSliceWithNulError::InteriorNul(nul)),
+            None => Err(FromSliceWithNulError::NotNulTerminated)
+        }
+    }
+
+    /// Wraps the start of a slice up to and including its first null.
+    pub fn from_bytes_until_nul(bytes: &[u8]) -> Result<&Self, FromSliceWithNulError> {
+        match bytes.iter().position(|&byte| byte == 0) {
+            Some(nul) => Ok(unsafe { CStr8::from_bytes_with_nul_unchecked(&bytes[..= nul]) }),
+            None => Err(FromSliceWithNulError::NotNulTerminated)
+        }
+    }
+
+    /// Wraps a slice without checking it, it must end with its only null.
+    pub const unsafe fn from_bytes_with_nul_unchecked(bytes: &[u8]) -> &Self {
+        &*(bytes as *const [u8] as *const CStr8)
+    }
+
+    /// Wraps a null-terminated string, e.g. one referenced by a firmware table.
+    pub unsafe fn from_ptr<'a>(ptr: *const u8) -> &'a Self {
+        let mut len = 0;
+        while *ptr.add(len) != 0 {
+            len += 1;
+        }
+        CStr8::from_bytes_with_nul_unchecked(slice::from_raw_parts(ptr, len + 1))
+    }
+
+    /// Encodes `string` as Latin-1 into `buffer` and returns the part of it holding the string.
+    pub fn from_str_with_buf<'a>(string: &str, buffer: &'a mut [u8]) -> Result<&'a Self, FromStrError> {
+        let mut len = 0;
+        for (offset, c) in string.char_indices() {
+            let byte = match c {
+                '\0' => return Err(FromStrError::InteriorNul(offset)),
+                c if (c as u32) > 0xFF => return Err(FromStrError::InvalidChar(offset)),
+                c => c as u8
+            };
+            *buffer.get_mut(len).ok_or(FromStrError::BufferTooSmall)? = byte;
+            len += 1;
+        }
+        *buffer.get_mut(len).ok_or(FromStrError::BufferTooSmall)? = 0;
+        Ok(unsafe { CStr8::from_bytes_with_nul_unchecked(&buffer[..= len]) })
+    }
+
+    /// Encodes the string as UCS-2 into `buffer`, which needs `len() + 1` code units.
+    pub fn to_cstr16_with_buf<'a>(&self, buffer: &'a mut [u16]) -> Result<&'a CStr16, FromStrError> {
+        let buffer = buffer.get_mut(.. self.0.len()).ok_or(FromStrError::BufferTooSmall)?;
+        for (code, &byte) in buffer.iter_mut().zip(self.0.iter()) {
+            *code = byte as u16;
+        }
+        Ok(unsafe { CStr16::from_u16_with_nul_unchecked(buffer) })
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.0.as_ptr()
+    }
+
+    /// Returns the bytes without the terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[.. self.len()]
+    }
+
+    pub fn as_bytes_with_nul(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the number of bytes without the terminator.
+    pub fn len(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the content as `&str` if it is ASCII, which reads the same in Latin-1 and UTF-8.
+    pub fn as_ascii_str(&self) -> Option<&str> {
+        if self.as_bytes().is_ascii() {
+            core::str::from_utf8(self.as_bytes()).ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn chars(&self) -> Latin1Chars<'_> {
+        Latin1Chars(self.as_bytes().iter())
+    }
+}
+
+impl fmt::Display for CStr8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.chars() {
+            fmt::Write::write_char(f, c)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CStr8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Write::write_char(f, '"')?;
+        for c in self.chars().flat_map(char::escape_debug) {
+            fmt::Write::write_char(f, c)?;
+        }
+        fmt::Write::write_char(f, '"')
+    }
+}
+
+impl PartialEq<str> for CStr8 {
+    fn eq(&self, other: &str) -> bool {
+        self.chars().eq(other.chars())
+    }
+}
+
+impl<'a> PartialEq<&'a str> for CStr8 {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+impl Default for &CStr8 {
+    fn default() -> Self {
+        unsafe { CStr8::from_bytes_with_nul_unchecked(&[0]) }
+    }
+}
+
+impl AsRef<CStr8> for CStr8 {
+    fn as_ref(&self) -> &CStr8 {
+        self
+    }
+}
+
+/// Iterator over the characters of a `CStr8`.
+#[derive(Clone, Debug)]
+pub struct Latin1Chars<'a>(slice::Iter<'a, u8>);
+
+impl<'a> Iterator for Latin1Chars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.0.next().map(|&byte| byte as char)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Latin1Chars<'a> {}
+
+fn is_surrogate(code: u16) -> bool {
+    (0xD800 ..= 0xDFFF).contains(&code)
+}
+
+/// Returns the number of code units of a string literal including the terminator, used by `cstr16!`.
+#[doc(hidden)]
+pub const fn literal_len(string: &str) -> usize {
+    let bytes = string.as_bytes();
+    let mut i = 0;
+    let mut len = 1;
+    while i < bytes.len() {
+        i += utf8_width(bytes[i]);
+        len += 1;
+    }
+    len
+}
+
+/// Encodes a string literal at compile time, used by `cstr16!`.
+#[doc(hidden)]
+pub const fn encode_literal<const N: usize>(string: &str) -> [u16; N] {
+    let bytes = string.as_bytes();
+    let mut codes = [0u16; N];
+    let mut i = 0;
+    let mut j = 0;
+    while i < bytes.len() {
+        let code = match utf8_width(bytes[i]) {
+            1 => bytes[i] as u16,
+            2 => (bytes[i] as u16 & 0x1F) << 6 | (bytes[i + 1] as u16 & 0x3F),
+            _ => (bytes[i] as u16 & 0x0F) << 12 | (bytes[i + 1] as u16 & 0x3F) << 6 | (bytes[i + 2] as u16 & 0x3F)
+        };
+        if code == 0 {
+            panic!("null character in UCS-2 literal");
+        }
+        codes[j] = code;
+        i += utf8_width(bytes[i]);
+        j += 1;
+    }
+    codes
+}
+
+const fn utf8_width(lead: u8) -> usize {
+    if lead < 0x80 {
+        1
+    } else if lead < 0xE0 {
+        2
+    } else if lead < 0xF0 {
+        3
+    } else {
+        panic!("character outside the Basic Multilingual Plane in UCS-2 literal")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn cstr16_with_nul() {
+        let string = CStr16::from_u16_with_nul(&[0x48, 0x69, 0]).unwrap();
+        assert_eq!(string, "Hi");
+        assert_eq!(string.len(), 2);
+        assert_eq!(string.size_in_bytes(), 6);
+        assert_eq!(string.as_slice_with_nul(), &[0x48, 0x69, 0]);
+
+        assert!(CStr16::from_u16_with_nul(&[0]).unwrap().is_empty());
+        assert_eq!(CStr16::from_u16_with_nul(&[0x48, 0, 0x69, 0]), Err(FromSliceWithNulError::InteriorNul(1)));
+        assert_eq!(CStr16::from_u16_with_nul(&[0x48, 0x69]), Err(FromSliceWithNulError::NotNulTerminated));
+        assert_eq!(CStr16::from_u16_with_nul(&[]), Err(FromSliceWithNulError::NotNulTerminated));
+        assert_eq!(CStr16::from_u16_with_nul(&[0x48, 0xD83D, 0xDE00, 0]), Err(FromSliceWithNulError::InvalidChar(1)));
+        assert_eq!(CStr16::from_u16_with_nul(&[0xDFFF, 0]), Err(FromSliceWithNulError::InvalidChar(0)));
+    }
+
+    #[test]
+    fn cstr16_until_nul() {
+        let string = CStr16::from_u16_until_nul(&[0x41, 0, 0xD800, 0]).unwrap();
+        assert_eq!(string, "A");
+        assert_eq!(CStr16::from_u16_until_nul(&[0x41, 0x42]), Err(FromSliceWithNulError::NotNulTerminated));
+        assert_eq!(CStr16::from_u16_until_nul(&[0xD800, 0]), Err(FromSliceWithNulError::InvalidChar(0)));
+    }
+
+    #[test]
+    fn cstr16_from_ptr() {
+        let codes = [0x4F, 0x56, 0x4D, 0x46, 0, 0x41];
+        let string = unsafe { CStr16::from_ptr(codes.as_ptr()) }.unwrap();
+        assert_eq!(string, "OVMF");
+        assert_eq!(string.as_ptr(), codes.as_ptr());
+
+        let surrogate = [0x41, 0xDBFF, 0];
+        assert_eq!(unsafe { CStr16::from_ptr(surrogate.as_ptr()) }, Err(FromSliceWithNulError::InvalidChar(1)));
+    }
+
+    #[test]
+    fn cstr16_from_str() {
+        let mut buffer = [0xFFFF; 8];
+        let string = CStr16::from_str_with_buf("\u{e9}t\u{e9}", &mut buffer).unwrap();
+        assert_eq!(string.as_slice_with_nul(), &[0xE9, 0x74, 0xE9, 0]);
+        assert_eq!(string.to_string(), "\u{e9}t\u{e9}");
+
+        assert_eq!(CStr16::from_str_with_buf("a\0b", &mut buffer), Err(FromStrError::InteriorNul(1)));
+        assert_eq!(CStr16::from_str_with_buf("a\u{1F600}", &mut buffer), Err(FromStrError::InvalidChar(1)));
+        assert_eq!(CStr16::from_str_with_buf("abcdefgh", &mut buffer), Err(FromStrError::BufferTooSmall));
+        assert!(CStr16::from_str_with_buf("abcdefg", &mut buffer).is_ok());
+        assert_eq!(Status::from(FromStrError::BufferTooSmall), Status::BufferTooSmall);
+        assert_eq!(Status::from(FromSliceWithNulError::NotNulTerminated), Status::CompromisedData);
+    }
+
+    #[test]
+    fn cstr16_formatting() {
+        let string = cstr16!("Tab\there \"x\"");
+        assert_eq!(string.to_string(), "Tab\there \"x\"");
+        assert_eq!(format!("{:?}", string), "\"Tab\\there \\\"x\\\"\"");
+        assert_eq!(<&CStr16>::default(), "");
+    }
+
+    #[test]
+    fn cstr16_literals() {
+        assert_eq!(cstr16!("").as_slice_with_nul(), &[0]);
+        assert_eq!(cstr16!("Boot0001").as_slice(), CStr16::from_u16_with_nul(&[0x42, 0x6F, 0x6F, 0x74, 0x30, 0x30, 0x30, 0x31, 0]).unwrap().as_slice());
+        // Two and three byte UTF-8 sequences.
+        assert_eq!(cstr16!("\u{e9}\u{20AC}\u{FFFD}").as_slice(), &[0xE9, 0x20AC, 0xFFFD]);
+        assert_eq!(literal_len("\u{e9}\u{20AC}"), 3);
+    }
+
+    #[test]
+    fn cstr8() {
+        let string = CStr8::from_bytes_with_nul(b"caf\xe9\0").unwrap();
+        assert_eq!(string, "caf\u{e9}");
+        assert_eq!(string.as_ascii_str(), None);
+        assert_eq!(CStr8::from_bytes_until_nul(b"ACPI\0 junk").unwrap().as_ascii_str(), Some("ACPI"));
+        assert_eq!(CStr8::from_bytes_with_nul(b"a\0b\0"), Err(FromSliceWithNulError::InteriorNul(1)));
+        assert_eq!(CStr8::from_bytes_with_nul(b"ab"), Err(FromSliceWithNulError::NotNulTerminated));
+
+        let mut buffer = [0u16; 5];
+        assert_eq!(string.to_cstr16_with_buf(&mut buffer).unwrap(), "caf\u{e9}");
+        assert_eq!(string.to_cstr16_with_buf(&mut buffer[.. 4]), Err(FromStrError::BufferTooSmall));
+
+        let mut bytes = [0u8; 4];
+        assert_eq!(CStr8::from_str_with_buf("\u{20AC}", &mut bytes), Err(FromStrError::InvalidChar(0)));
+    }
+}
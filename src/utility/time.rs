@@ -7,6 +7,9 @@
 
 // See also http://wiki.phoenix.com/wiki/index.php/EFI_TIME
 
+//* Constants & Types *//
+const SECONDS_PER_DAY: i64 = 86400;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
 #[repr(C)]
 pub struct Time {
@@ -43,28 +46,63 @@ pub struct Time {
     _pad2: u8
 }
 
+/// The time's offset in minutes from local time to UTC, such that `local = UTC - offset`.
+///
+/// The sign follows the specification and is the opposite of ISO 8601,
+/// e.g. Pacific Standard Time (UTC-08:00) is 480 and Tokyo (UTC+09:00) is -540.
+///
+/// Firmware reports `TimeZone::UNSPECIFIED` (2047) when the time is a plain local time
+/// with no known relation to UTC.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-#[repr(i16)]
-pub enum TimeZone {
-    Unspecified = 0x07FF
+#[repr(transparent)]
+pub struct TimeZone(i16);
+
+impl TimeZone {
+    /// EFI_UNSPECIFIED_TIMEZONE
+    pub const UNSPECIFIED: TimeZone = TimeZone(0x07FF);
+    pub const UTC: TimeZone = TimeZone(0);
+
+    /// Creates a time zone from an offset in minutes, which must be within -1440 ~ 1440.
+    pub fn from_minutes(minutes: i16) -> Option<Self> {
+        if (-1440..=1440).contains(&minutes) {
+            Some(TimeZone(minutes))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the offset in minutes, or `None` if the time zone is unspecified.
+    pub fn minutes(&self) -> Option<i16> {
+        if self.is_specified() {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_specified(&self) -> bool {
+        self.0 >= -1440 && self.0 <= 1440
+    }
+
+    pub fn raw(&self) -> i16 {
+        self.0
+    }
 }
 
 impl Default for TimeZone {
     fn default() -> Self {
-        TimeZone::Unspecified
+        TimeZone::UNSPECIFIED
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-#[repr(u8)]
-pub enum Daylight {
-    Adjust = 0x01,
-    In = 0x02
-}
-
-impl Default for Daylight {
-    fn default() -> Self {
-        Daylight::Adjust
+bitflags! {
+    /// Daylight savings time information of a `Time`.
+    #[derive(Default)]
+    pub struct Daylight: u8 {
+        /// The time is affected by daylight savings time.
+        const ADJUST = 0x01;
+        /// The time has been adjusted for daylight savings time.
+        const IN = 0x02;
     }
 }
 
@@ -77,19 +115,249 @@ pub struct TimeCapablities {
 }
 
 impl Time {
+    /// Creates a new time, returns `None` if any of the fields is out of range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8, nano_second: u32, time_zone: TimeZone, day_light: Daylight) -> Option<Self> {
+        let valid = (1900..=9999).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1 && day <= days_in_month(year, month)
+            && hour <= 23 && minute <= 59 && second <= 59
+            && nano_second <= 999_999_999
+            && (time_zone.is_specified() || time_zone == TimeZone::UNSPECIFIED);
+
+        if !valid {
+            return None;
+        }
+
+        Some(Time {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            _pad1: 0,
+            nano_second,
+            time_zone,
+            day_light,
+            _pad2: 0
+        })
+    }
+
+    /// Creates a UTC time from seconds since 1970-01-01 00:00:00 UTC.
+    pub fn from_unix_timestamp(seconds: i64, nano_second: u32) -> Option<Self> {
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let secs = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+
+        if !(1900..=9999).contains(&year) {
+            return None;
+        }
+
+        Time::new(year as u16, month, day,
+                  (secs / 3600) as u8, (secs % 3600 / 60) as u8, (secs % 60) as u8,
+                  nano_second, TimeZone::UTC, Daylight::empty())
+    }
+
     pub fn format_time(&self) -> &str { // Unused
         ""
     }
 
     pub fn year(&self) -> u16 {
-        (self.year)
+        self.year
     }
 
     pub fn month(&self) -> u8 {
-        (self.month)
+        self.month
     }
 
     pub fn day(&self) -> u8 {
-        (self.day)
+        self.day
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    pub fn nano_second(&self) -> u32 {
+        self.nano_second
+    }
+
+    pub fn time_zone(&self) -> TimeZone {
+        self.time_zone
+    }
+
+    pub fn day_light(&self) -> Daylight {
+        self.day_light
+    }
+
+    /// Returns the offset in minutes from local time to UTC, such that `UTC = local + offset`,
+    /// or `None` if the time zone is unspecified.
+    ///
+    /// `Daylight::IN` is not added: the time zone is the offset in effect for this time,
+    /// so it already includes daylight savings time and the flag is informational only.
+    pub fn utc_offset(&self) -> Option<i32> {
+        self.time_zone.minutes().map(|minutes| minutes as i32)
+    }
+
+    /// Returns seconds since 1970-01-01 00:00:00 UTC, or `None` if the time zone is unspecified.
+    pub fn unix_timestamp(&self) -> Option<i64> {
+        let offset = self.utc_offset()? as i64;
+        Some(self.local_timestamp() + offset * 60)
     }
-}
\ No newline at end of file
+
+    /// Converts this time to UTC, or returns `None` if the time zone is unspecified.
+    pub fn to_utc(&self) -> Option<Self> {
+        Time::from_unix_timestamp(self.unix_timestamp()?, self.nano_second)
+    }
+
+    /// Converts this time to UTC, interpreting an unspecified time zone as `assume`.
+    pub fn to_utc_assuming(&self, assume: TimeZone) -> Option<Self> {
+        if self.time_zone.is_specified() {
+            self.to_utc()
+        } else {
+            let mut time = *self;
+            time.time_zone = assume;
+            time.to_utc()
+        }
+    }
+
+    fn local_timestamp(&self) -> i64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        days * SECONDS_PER_DAY + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+// See also http://howardhinnant.github.io/date_algorithms.html
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Time {
+        Time::new(year, month, day, hour, minute, second, 0, TimeZone::UTC, Daylight::empty()).unwrap()
+    }
+
+    #[test]
+    fn days_at_known_dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1900, 1, 1), -25567);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-25567), (1900, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn days_round_trip() {
+        for days in days_from_civil(1899, 1, 1) .. days_from_civil(2101, 12, 31) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month as i64, day as i64), days);
+        }
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(!is_leap_year(1900));
+        assert!(is_leap_year(2000));
+        assert!(is_leap_year(2024));
+        assert!(!is_leap_year(2100));
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert!(Time::new(2000, 2, 29, 0, 0, 0, 0, TimeZone::UTC, Daylight::empty()).is_some());
+        assert!(Time::new(2100, 2, 29, 0, 0, 0, 0, TimeZone::UTC, Daylight::empty()).is_none());
+        assert_eq!(civil_from_days(days_from_civil(2100, 2, 28) + 1), (2100, 3, 1));
+    }
+
+    #[test]
+    fn unix_timestamps() {
+        assert_eq!(utc(1970, 1, 1, 0, 0, 0).unix_timestamp(), Some(0));
+        assert_eq!(utc(1900, 1, 1, 0, 0, 0).unix_timestamp(), Some(-2_208_988_800));
+        assert_eq!(utc(2000, 2, 29, 12, 30, 15).unix_timestamp(), Some(951_827_415));
+        assert_eq!(utc(2100, 3, 1, 0, 0, 0).unix_timestamp(), Some(4_107_542_400));
+
+        assert_eq!(Time::from_unix_timestamp(0, 0), Some(utc(1970, 1, 1, 0, 0, 0)));
+        assert_eq!(Time::from_unix_timestamp(-1, 0), Some(utc(1969, 12, 31, 23, 59, 59)));
+        assert_eq!(Time::from_unix_timestamp(-2_208_988_800, 0), Some(utc(1900, 1, 1, 0, 0, 0)));
+        assert_eq!(Time::from_unix_timestamp(-2_208_988_801, 0), None);
+        assert_eq!(Time::from_unix_timestamp(951_827_415, 0), Some(utc(2000, 2, 29, 12, 30, 15)));
+    }
+
+    #[test]
+    fn time_zones() {
+        // Tokyo is UTC+09:00, which the specification writes as -540.
+        let tokyo = TimeZone::from_minutes(-540).unwrap();
+        let time = Time::new(2000, 1, 1, 9, 0, 0, 0, tokyo, Daylight::empty()).unwrap();
+        assert_eq!(time.unix_timestamp(), Some(946_684_800));
+        assert_eq!(time.to_utc(), Some(utc(2000, 1, 1, 0, 0, 0)));
+
+        // Pacific Standard Time is UTC-08:00, which the specification writes as 480.
+        let pst = TimeZone::from_minutes(480).unwrap();
+        let time = Time::new(1999, 12, 31, 16, 0, 0, 0, pst, Daylight::ADJUST).unwrap();
+        assert_eq!(time.unix_timestamp(), Some(946_684_800));
+        assert_eq!(time.to_utc(), Some(utc(2000, 1, 1, 0, 0, 0)));
+
+        // Central European Summer Time is UTC+02:00, the offset already includes daylight savings time.
+        let summer = Time::new(2000, 7, 1, 2, 0, 0, 0, TimeZone::from_minutes(-120).unwrap(), Daylight::ADJUST | Daylight::IN).unwrap();
+        assert_eq!(summer.utc_offset(), Some(-120));
+        assert_eq!(summer.to_utc(), Some(utc(2000, 7, 1, 0, 0, 0)));
+
+        assert_eq!(TimeZone::from_minutes(1441), None);
+        assert_eq!(TimeZone::UNSPECIFIED.minutes(), None);
+    }
+
+    #[test]
+    fn unspecified_time_zone() {
+        let local = Time::new(2000, 1, 1, 9, 0, 0, 0, TimeZone::UNSPECIFIED, Daylight::empty()).unwrap();
+        assert_eq!(local.utc_offset(), None);
+        assert_eq!(local.unix_timestamp(), None);
+        assert_eq!(local.to_utc(), None);
+        assert_eq!(local.to_utc_assuming(TimeZone::UTC), Some(utc(2000, 1, 1, 9, 0, 0)));
+        assert!(Time::new(2000, 1, 1, 0, 0, 0, 0, TimeZone(2000), Daylight::empty()).is_none());
+    }
+}